@@ -0,0 +1,150 @@
+use specs::prelude::*;
+use rltk::Point;
+use super::{Viewshed, Follower, Monster, CombatStats, Map, Position, WantsToMelee, RunState, StatusEffects,
+            Initiative, ActionType, status_effect_system::can_act, initiative_system::ACTION_THRESHOLD};
+
+/// Allies wander no further than this from their leader when there's nothing to fight.
+const FOLLOW_DISTANCE : f32 = 2.5;
+
+/// Allies attack the nearest hostile they can see, and otherwise keep close to their leader.
+pub struct AllyAI {}
+
+impl<'a> System<'a> for AllyAI {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadExpect<'a, RunState>,
+                        Entities<'a>,
+                        WriteStorage<'a, Viewshed>,
+                        ReadStorage<'a, Follower>,
+                        ReadStorage<'a, Monster>,
+                        ReadStorage<'a, CombatStats>,
+                        WriteStorage<'a, Position>,
+                        WriteStorage<'a, WantsToMelee>,
+                        ReadStorage<'a, StatusEffects>,
+                        WriteStorage<'a, Initiative> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, runstate, entities, mut viewsheds, followers, monsters, combat_stats, mut positions,
+            mut wants_to_melee, statuses, mut initiatives) = data;
+
+        if *runstate != RunState::Ticking { return; }
+
+        let hostiles : Vec<(Entity, Point)> = (&entities, &monsters, &combat_stats, &positions).join()
+            .map(|(entity, _, _, pos)| (entity, Point::new(pos.x, pos.y)))
+            .collect();
+        let leaders : Vec<(Entity, Point)> = (&entities, &positions).join()
+            .map(|(entity, pos)| (entity, Point::new(pos.x, pos.y)))
+            .filter(|(entity, _)| (&followers).join().any(|f| f.leader == *entity))
+            .collect();
+
+        for (entity, follower, viewshed, pos, initiative) in (&entities, &followers, &mut viewsheds, &mut positions, &mut initiatives).join() {
+            if initiative.energy < ACTION_THRESHOLD { continue; }
+            if !can_act(statuses.get(entity)) {
+                initiative.spend(ActionType::Wait);
+                continue;
+            }
+
+            let here = Point::new(pos.x, pos.y);
+            let nearest_hostile = hostiles.iter()
+                .filter(|(_, hostile_pos)| viewshed.visible_tiles.contains(hostile_pos))
+                .map(|(hostile, hostile_pos)| (*hostile, *hostile_pos, rltk::DistanceAlg::Pythagoras.distance2d(here, *hostile_pos)))
+                .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+
+            let destination = match nearest_hostile {
+                Some((hostile, _, distance)) if distance < 1.5 => {
+                    initiative.spend(ActionType::Attack);
+                    wants_to_melee.insert(entity, WantsToMelee{ target: hostile }).expect("Unable to insert attack");
+                    continue;
+                }
+                Some((_, hostile_pos, _)) => Some(hostile_pos),
+                None => leaders.iter()
+                    .find(|(leader, _)| *leader == follower.leader)
+                    .map(|(_, leader_pos)| *leader_pos)
+                    .filter(|leader_pos| rltk::DistanceAlg::Pythagoras.distance2d(here, *leader_pos) > FOLLOW_DISTANCE)
+            };
+
+            let Some(destination) = destination else {
+                initiative.spend(ActionType::Wait);
+                continue;
+            };
+
+            // Whoever is standing at the destination blocks it, so open it up just for the search
+            initiative.spend(ActionType::Move);
+            let target_index = map.xy_index(destination.x, destination.y);
+            let target_blocked = map.blocked[target_index];
+            map.blocked[target_index] = false;
+            let path = rltk::a_star_search(map.xy_index(pos.x, pos.y), target_index, &*map);
+            map.blocked[target_index] = target_blocked;
+            if path.success && path.steps.len() > 1 && !map.blocked[path.steps[1]] {
+                let mut index = map.xy_index(pos.x, pos.y);
+                map.blocked[index] = false;
+                pos.x = path.steps[1] as i32 % map.width;
+                pos.y = path.steps[1] as i32 / map.width;
+                index = map.xy_index(pos.x, pos.y);
+                map.blocked[index] = true;
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{TileType, initiative_system::NORMAL_SPEED};
+
+    /// An open 10x10 room with the leader at 1,1 and a ready dog at `x`,`y` that can see the whole room.
+    fn room_with_dog(x : i32, y : i32) -> (World, Entity, Entity) {
+        let mut ecs = World::new();
+        ecs.register::<Viewshed>();
+        ecs.register::<Follower>();
+        ecs.register::<Monster>();
+        ecs.register::<CombatStats>();
+        ecs.register::<Position>();
+        ecs.register::<WantsToMelee>();
+        ecs.register::<StatusEffects>();
+        ecs.register::<Initiative>();
+        ecs.insert(RunState::Ticking);
+        ecs.insert(Map{ tiles: vec![TileType::Floor; 100], width: 10, height: 10, blocked: vec![false; 100],
+            tile_content: vec![Vec::new(); 100], ..Default::default() });
+
+        let leader = ecs.create_entity().with(Position{ x: 1, y: 1 }).build();
+        let everywhere = (0..10).flat_map(|x| (0..10).map(move |y| Point::new(x, y))).collect();
+        let dog = ecs.create_entity()
+            .with(Position{ x, y })
+            .with(Follower{ leader })
+            .with(Viewshed{ visible_tiles: everywhere, range: 8, dirty: false })
+            .with(Initiative{ speed: NORMAL_SPEED, energy: ACTION_THRESHOLD })
+            .build();
+        (ecs, leader, dog)
+    }
+
+    fn position(ecs : &World, entity : Entity) -> (i32, i32) {
+        let positions = ecs.read_storage::<Position>();
+        let pos = positions.get(entity).unwrap();
+        (pos.x, pos.y)
+    }
+
+    #[test]
+    fn allies_attack_adjacent_hostiles() {
+        let (mut ecs, _, dog) = room_with_dog(5, 5);
+        let orc = ecs.create_entity().with(Monster{}).with(CombatStats{ max_hp: 5, hp: 5 }).with(Position{ x: 6, y: 5 }).build();
+        AllyAI{}.run_now(&ecs);
+        assert_eq!(ecs.read_storage::<WantsToMelee>().get(dog).map(|w| w.target), Some(orc));
+    }
+
+    #[test]
+    fn allies_catch_up_with_their_leader() {
+        let (ecs, _, dog) = room_with_dog(6, 6);
+        AllyAI{}.run_now(&ecs);
+        assert_eq!(position(&ecs, dog), (5, 5));
+    }
+
+    #[test]
+    fn allies_close_to_their_leader_stay_put() {
+        let (ecs, _, dog) = room_with_dog(2, 2);
+        AllyAI{}.run_now(&ecs);
+        assert_eq!(position(&ecs, dog), (2, 2));
+        assert!(ecs.read_storage::<Initiative>().get(dog).unwrap().energy < ACTION_THRESHOLD);
+    }
+}
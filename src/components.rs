@@ -2,13 +2,13 @@ use specs::prelude::*;
 use specs_derive::*;
 use rltk::{RGB};
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Renderable {
     pub glyph: rltk::FontCharType,
     pub foreground: RGB,
@@ -16,33 +16,156 @@ pub struct Renderable {
     pub render_order : i32
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct Player {}
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Viewshed {
     pub visible_tiles : Vec<rltk::Point>,
     pub range : i32,
     pub dirty : bool
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct Monster {}
 
-#[derive(Component, Debug)]
+/// Speed is the energy gained each turn; an entity acts once it has banked an action's worth.
+#[derive(Component, Debug, Clone)]
+pub struct Initiative {
+    pub speed : i32,
+    pub energy : i32
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ActionType { Move, Sneak, Attack, Fire, UseItem, PickUp, Drop, Wait }
+
+impl ActionType {
+    pub fn cost(&self) -> i32 {
+        match self {
+            ActionType::Move => 100,
+            ActionType::Sneak => 150,
+            ActionType::Attack => 100,
+            ActionType::Fire => 120,
+            ActionType::UseItem => 100,
+            ActionType::PickUp => 50,
+            ActionType::Drop => 50,
+            ActionType::Wait => 100
+        }
+    }
+}
+
+impl Initiative {
+    pub fn spend(&mut self, action : ActionType) {
+        self.energy -= action.cost();
+    }
+}
+
+#[derive(Component, Debug, Clone)]
 pub struct Name {
     pub name : String
 }
 
-#[derive(Component, Debug)]
+/// A monster that hasn't noticed the player yet. It won't hunt them until a perception
+/// check succeeds, either on seeing them or on hearing a noise.
+#[derive(Component, Debug, Clone)]
+pub struct Unaware {
+    pub asleep : bool
+}
+
+/// An ally, such as a pet, that follows `leader` around and fights whatever it fights.
+#[derive(Component, Debug, Clone)]
+pub struct Follower {
+    pub leader : Entity
+}
+
+/// A monster's nerve. It flees once `value` drops below `morale_system::FLEE_BELOW`,
+/// and turns to fight again when it has recovered.
+#[derive(Component, Debug, Clone)]
+pub struct Morale {
+    pub value : i32,
+    pub max : i32,
+    pub fleeing : bool
+}
+
+/// Creatures with this never heal on their own.
+#[derive(Component, Debug, Clone)]
+pub struct NoRegeneration {}
+
+/// The player is moving carefully: slower, but much quieter.
+#[derive(Component, Debug, Clone)]
+pub struct Sneaking {}
+
+#[derive(Component, Debug, Clone)]
 pub struct BlocksTile {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct CombatStats {
     pub max_hp : i32,
-    pub hp : i32,
+    pub hp : i32
+}
+
+/// Primary attributes; 10 is average. See `gamesystem` for what each one feeds.
+#[derive(Component, Debug, Clone)]
+pub struct Attributes {
+    pub might : i32,
+    pub fitness : i32,
+    pub quickness : i32,
+    pub intelligence : i32
+}
+
+impl Default for Attributes {
+    fn default() -> Self {
+        Attributes{ might: 10, fitness: 10, quickness: 10, intelligence: 10 }
+    }
+}
+
+#[derive(Component, Debug, Clone, Default)]
+pub struct Skills {
+    pub melee : i32,
     pub defense : i32,
-    pub power : i32
+    pub magic : i32,
+    pub stealth : i32
+}
+
+/// Damage rolled on a successful melee hit, written as `n_dice`d`die_type`+`bonus` (e.g. 1d6+2).
+#[derive(Component, Debug, Clone, Copy)]
+pub struct DamageDice {
+    pub n_dice : i32,
+    pub die_type : i32,
+    pub bonus : i32
+}
+
+impl std::fmt::Display for DamageDice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.bonus {
+            0 => write!(f, "{}d{}", self.n_dice, self.die_type),
+            b if b > 0 => write!(f, "{}d{}+{}", self.n_dice, self.die_type, b),
+            b => write!(f, "{}d{}{}", self.n_dice, self.die_type, b)
+        }
+    }
+}
+
+/// Experience earned by whoever lands the killing blow.
+#[derive(Component, Debug, Clone)]
+pub struct XpValue {
+    pub xp : i32
+}
+
+/// `xp` counts towards the next level and is used up when levelling.
+#[derive(Component, Debug, Clone)]
+pub struct Experience {
+    pub level : i32,
+    pub xp : i32
+}
+
+impl Experience {
+    pub fn xp_to_next_level(&self) -> i32 {
+        self.level * 50
+    }
+
+    pub fn can_level_up(&self) -> bool {
+        self.xp >= self.xp_to_next_level()
+    }
 }
 
 #[derive(Component, Debug, Clone)]
@@ -50,58 +173,375 @@ pub struct WantsToMelee {
     pub target : Entity
 }
 
-#[derive(Component, Debug)]
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum DamageType { Physical, Fire, Cold, Poison, Acid, Magic }
+
+impl std::fmt::Display for DamageType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DamageType::Physical => "physical",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Poison => "poison",
+            DamageType::Acid => "acid",
+            DamageType::Magic => "magic"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Resisted damage is halved, immune damage is ignored and weaknesses take double.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Resistances {
+    pub resist : Vec<DamageType>,
+    pub immune : Vec<DamageType>,
+    pub weak : Vec<DamageType>
+}
+
+/// Each entry is the amount, its type and who dealt it (if anyone did).
+#[derive(Component, Debug, Clone)]
 pub struct SufferDamage {
-    pub amount : Vec<i32>
+    pub amount : Vec<(i32, DamageType, Option<Entity>)>
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32, damage_type: DamageType, source: Option<Entity>) {
         if let Some(suffering) = store.get_mut(victim) {
-            suffering.amount.push(amount);
+            suffering.amount.push((amount, damage_type, source));
         } else {
-            let dmg = SufferDamage { amount : vec![amount] };
+            let dmg = SufferDamage { amount : vec![(amount, damage_type, source)] };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
     }
 }
 
-#[derive(Component, Debug)]
+/// Added by the blow that takes an entity below 1 hp.
+#[derive(Component, Debug, Clone)]
+pub struct Slain {
+    pub killer : Option<Entity>,
+    pub cause : String
+}
+
+#[derive(Component, Debug, Clone)]
 pub struct Item {}
 
-#[derive(Component, Debug)]
+/// How heavy one of this item is, in pounds.
+#[derive(Component, Debug, Clone)]
+pub struct Weight {
+    pub lbs : f32
+}
+
+/// What an entity is carrying against what it can carry. Over `capacity` it is burdened;
+/// it can't pick up anything that would take it over the hard limit.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Encumbrance {
+    pub load : f32,
+    pub capacity : f32
+}
+
+impl Encumbrance {
+    /// How many times the capacity can be carried at all, if slowly.
+    pub const HARD_LIMIT : f32 = 2.0;
+
+    pub fn burdened(&self) -> bool {
+        self.load > self.capacity
+    }
+
+    pub fn can_carry(&self, lbs : f32) -> bool {
+        self.load + lbs <= self.capacity * Encumbrance::HARD_LIMIT
+    }
+}
+
+#[derive(Component, Debug, Clone)]
 pub struct Consumable {}
 
-#[derive(Component, Debug)]
+/// How much wear gear can take. Broken gear (at 0) gives none of its bonuses.
+#[derive(Component, Debug, Clone)]
+pub struct Durability {
+    pub current : i32,
+    pub max : i32
+}
+
+impl Durability {
+    pub fn broken(&self) -> bool {
+        self.current < 1
+    }
+
+    pub fn percent(&self) -> i32 {
+        i32::max(0, self.current * 100 / i32::max(1, self.max))
+    }
+}
+
+/// Gear made of metal, which rusts.
+#[derive(Component, Debug, Clone)]
+pub struct Metal {}
+
+/// Metal gear that has been eaten into by rust.
+#[derive(Component, Debug, Clone)]
+pub struct Rusty {}
+
+/// A creature whose touch rusts metal gear rather than doing damage, such as the Rust Monster.
+#[derive(Component, Debug, Clone)]
+pub struct Corrodes {}
+
+/// Using this restores everything the user carries to full durability.
+#[derive(Component, Debug, Clone)]
+pub struct RepairsItems {}
+
+/// Uses left in an item, such as a wand, that isn't used up when it's empty.
+#[derive(Component, Debug, Clone)]
+pub struct Charges {
+    pub current : i32,
+    pub max : i32
+}
+
+/// Reading this refills every charged item the reader is carrying.
+#[derive(Component, Debug, Clone)]
+pub struct Recharges {}
+
+/// What a magic item is called until its kind has been identified.
+#[derive(Component, Debug, Clone)]
+pub struct ObfuscatedName {
+    pub name : String
+}
+
+/// Cursed equipment can't be taken off, and cursed scrolls turn on their reader.
+#[derive(Component, Debug, Clone)]
+pub struct Cursed {}
+
+/// Reading this lifts the curse from everything the reader is carrying.
+#[derive(Component, Debug, Clone)]
+pub struct RemovesCurse {}
+
+/// Reading this identifies every kind of magic item the reader is carrying.
+#[derive(Component, Debug, Clone)]
+pub struct IdentifiesItems {}
+
+#[derive(Component, Debug, Clone)]
+pub struct Mana {
+    pub current : i32,
+    pub max : i32
+}
+
+/// A spell is an entity with no position, carrying the same effect components as the items
+/// that do the same thing. Casting it is using it, for `mana_cost` instead of the item.
+#[derive(Component, Debug, Clone)]
+pub struct Spell {
+    pub mana_cost : i32
+}
+
+#[derive(Component, Debug, Clone, Default)]
+pub struct KnownSpells {
+    pub spells : Vec<Entity>
+}
+
+impl KnownSpells {
+    /// Whether a spell called the same as `spell` is already known. Every spellbook carries
+    /// its own copy of the spell it teaches, so the entities themselves can't be compared.
+    pub fn knows(&self, names : &ReadStorage<Name>, spell : Entity) -> bool {
+        let Some(name) = names.get(spell) else { return self.spells.contains(&spell) };
+        self.spells.iter().any(|known| names.get(*known).is_some_and(|n| n.name == name.name))
+    }
+}
+
+/// Reading this (a spellbook) adds `spell` to the reader's `KnownSpells`.
+#[derive(Component, Debug, Clone)]
+pub struct TeachesSpell {
+    pub spell : Entity
+}
+
+#[derive(Component, Debug, Clone)]
 pub struct Ranged {
     pub range : i32
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct InflictsDamage {
-    pub damage : i32
+    pub damage : i32,
+    pub damage_type : DamageType
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct AreaOfEffect {
     pub radius : i32
 }
 
-#[derive(Component, Debug)]
-pub struct Confusion {
-    pub turns : i32
+/// Items, and attackers or their weapons on a hit, with this shove targets `distance` tiles away.
+#[derive(Component, Debug, Clone)]
+pub struct Knockback {
+    pub distance : i32
+}
+
+/// Queued on an entity that is about to be pushed directly away from `origin`.
+#[derive(Component, Debug, Clone)]
+pub struct ForcedMovement {
+    pub origin : rltk::Point,
+    pub distance : i32,
+    pub source : Option<Entity>
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum StatusKind { Poison, Burning, Sleep, Slow, Haste, Paralysis, Confusion, Regeneration }
+
+/// How a new application of a status combines with one that is already running.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum StatusStacking { Refresh, Extend, Intensify }
+
+impl StatusKind {
+    pub fn stacking(&self) -> StatusStacking {
+        match self {
+            StatusKind::Poison => StatusStacking::Intensify,
+            StatusKind::Confusion => StatusStacking::Extend,
+            _ => StatusStacking::Refresh
+        }
+    }
+}
+
+impl std::fmt::Display for StatusKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let adjective = match self {
+            StatusKind::Poison => "poisoned",
+            StatusKind::Burning => "burning",
+            StatusKind::Sleep => "asleep",
+            StatusKind::Slow => "slowed",
+            StatusKind::Haste => "hasted",
+            StatusKind::Paralysis => "paralysed",
+            StatusKind::Confusion => "confused",
+            StatusKind::Regeneration => "regenerating"
+        };
+        write!(f, "{}", adjective)
+    }
+}
+
+/// `magnitude` is the damage per turn for poison and burning, the healing per turn for
+/// regeneration, and unused by the rest.
+#[derive(Copy, Clone, Debug)]
+pub struct StatusEffect {
+    pub kind : StatusKind,
+    pub turns : i32,
+    pub magnitude : i32
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, Default)]
+pub struct StatusEffects {
+    pub effects : Vec<StatusEffect>
+}
+
+impl StatusEffects {
+    pub fn get(&self, kind : StatusKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|e| e.kind == kind)
+    }
+
+    pub fn has(&self, kind : StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// Adds an effect, combining it with any running effect of the same kind.
+    pub fn add(&mut self, effect : StatusEffect) {
+        match self.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            None => self.effects.push(effect),
+            Some(existing) => {
+                match effect.kind.stacking() {
+                    StatusStacking::Refresh => {
+                        existing.turns = i32::max(existing.turns, effect.turns);
+                        existing.magnitude = i32::max(existing.magnitude, effect.magnitude);
+                    }
+                    StatusStacking::Extend => existing.turns += effect.turns,
+                    StatusStacking::Intensify => {
+                        existing.turns = i32::max(existing.turns, effect.turns);
+                        existing.magnitude += effect.magnitude;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns true if there was an effect of that kind to remove.
+    pub fn remove(&mut self, kind : StatusKind) -> bool {
+        let before = self.effects.len();
+        self.effects.retain(|e| e.kind != kind);
+        before != self.effects.len()
+    }
+
+    pub fn add_effect(store: &mut WriteStorage<StatusEffects>, victim: Entity, effect: StatusEffect) {
+        if let Some(statuses) = store.get_mut(victim) {
+            statuses.add(effect);
+        } else {
+            store.insert(victim, StatusEffects{ effects: vec![effect] }).expect("Unable to insert status");
+        }
+    }
+}
+
+/// Items (and attackers, on a hit) with this apply the status to their targets.
+#[derive(Component, Debug, Clone)]
+pub struct InflictsStatus {
+    pub kind : StatusKind,
+    pub turns : i32,
+    pub magnitude : i32
+}
+
+impl InflictsStatus {
+    pub fn effect(&self) -> StatusEffect {
+        StatusEffect{ kind: self.kind, turns: self.turns, magnitude: self.magnitude }
+    }
+}
+
+#[derive(Component, Debug, Clone)]
 pub struct ProvidesHealing {
     pub heal_amount : i32
 }
 
+/// Eating this resets the eater's `HungerClock` to well fed.
+#[derive(Component, Debug, Clone)]
+pub struct ProvidesFood {}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum HungerState { WellFed, Normal, Hungry, Starving }
+
+impl std::fmt::Display for HungerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            HungerState::WellFed => "Well Fed",
+            HungerState::Normal => "Normal",
+            HungerState::Hungry => "Hungry",
+            HungerState::Starving => "Starving"
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// `duration` is how many turns are left before the state drops to the next one.
+#[derive(Component, Debug, Clone)]
+pub struct HungerClock {
+    pub state : HungerState,
+    pub duration : i32
+}
+
+/// A stack of identical items held as one entity. Items that can stack carry this even
+/// when there is only one of them.
+#[derive(Component, Debug, Clone)]
+pub struct Quantity {
+    pub amount : i32
+}
+
+/// Carried by `owner`, which is either a creature or a container that is itself carried
+/// (or lying on the floor).
 #[derive(Component, Debug, Clone)]
 pub struct InBackpack {
     pub owner : Entity
 }
 
+/// An item that other items can be put in, up to `max_weight` pounds of them.
+#[derive(Component, Debug, Clone)]
+pub struct Container {
+    pub max_weight : f32,
+    pub scrolls_only : bool
+}
+
+/// Marks scrolls, for containers that only take scrolls.
+#[derive(Component, Debug, Clone)]
+pub struct Scroll {}
+
 #[derive(Component, Debug, Clone)]
 pub struct WantsToPickupItem {
     pub collected_by : Entity,
@@ -114,7 +554,150 @@ pub struct WantsToUseItem {
     pub target : Option<rltk::Point>
 }
 
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum EquipmentSlot { Melee, Ranged, Shield, Armour, Head, Hands, Feet }
+
+impl std::fmt::Display for EquipmentSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slot = match self {
+            EquipmentSlot::Melee => "in hand",
+            EquipmentSlot::Ranged => "readied",
+            EquipmentSlot::Shield => "on arm",
+            EquipmentSlot::Armour => "worn",
+            EquipmentSlot::Head => "on head",
+            EquipmentSlot::Hands => "on hands",
+            EquipmentSlot::Feet => "on feet"
+        };
+        write!(f, "{}", slot)
+    }
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Equippable {
+    pub slot : EquipmentSlot
+}
+
+/// Equipped items stay in their owner's backpack; this marks which slot they fill.
+#[derive(Component, Debug, Clone)]
+pub struct Equipped {
+    pub owner : Entity,
+    pub slot : EquipmentSlot
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct MeleePowerBonus {
+    pub power : i32
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct DefenseBonus {
+    pub defense : i32
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AmmoType { Arrow, Bolt }
+
+/// A bow or crossbow; firing it uses up one matching `Ammunition` from the backpack.
+#[derive(Component, Debug, Clone)]
+pub struct RangedWeapon {
+    pub range : i32,
+    pub ammo : AmmoType
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct Ammunition {
+    pub ammo : AmmoType
+}
+
+/// Can be thrown at a tile, where it lands and can be picked up again.
+#[derive(Component, Debug, Clone)]
+pub struct Throwable {
+    pub range : i32
+}
+
+/// `item` is either the ranged weapon being fired or the item being thrown.
+#[derive(Component, Debug, Clone)]
+pub struct WantsToFire {
+    pub item : Entity,
+    pub target : rltk::Point
+}
+
 #[derive(Component, Debug, Clone)]
 pub struct WantsToDropItem {
     pub item : Entity
 }
+
+#[derive(Component, Debug, Clone)]
+pub struct WantsToStoreItem {
+    pub item : Entity,
+    pub container : Entity
+}
+
+#[derive(Component, Debug, Clone)]
+pub struct WantsToRetrieveItem {
+    pub item : Entity
+}
+
+/// Marks a short-lived visual effect; it is deleted once `lifetime_ms` runs out.
+#[derive(Component, Debug, Clone)]
+pub struct ParticleLifetime {
+    pub lifetime_ms : f32
+}
+
+/// Hands every component type to `$then`, so that registering them and copying them between
+/// entities can't miss one that was added later. New components go on the end.
+macro_rules! all_components {
+    ($then:ident) => {{
+        use $crate::components::*;
+        $then!(
+            Position, Renderable, Player, Viewshed, Monster, Name, Initiative, BlocksTile, CombatStats, Attributes,
+            Skills, DamageDice, WantsToMelee, SufferDamage, Resistances, Item, ProvidesHealing, ProvidesFood,
+            HungerClock, InflictsDamage, AreaOfEffect, Consumable, Ranged, InBackpack, Quantity, Weight, Encumbrance,
+            ObfuscatedName, IdentifiesItems, Cursed, RemovesCurse, Charges, Recharges, Durability, Metal, Rusty,
+            Corrodes, RepairsItems, Container, Scroll, WantsToStoreItem, WantsToRetrieveItem, WantsToPickupItem,
+            WantsToUseItem, WantsToDropItem, StatusEffects, InflictsStatus, Equippable, Equipped, MeleePowerBonus,
+            DefenseBonus, RangedWeapon, Ammunition, Throwable, WantsToFire, Slain, XpValue, Experience, ParticleLifetime,
+            Knockback, ForcedMovement, Unaware, Sneaking, Morale, NoRegeneration, Mana, Follower, Spell, KnownSpells,
+            TeachesSpell
+        );
+    }};
+}
+pub(crate) use all_components;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spells_are_known_by_name() {
+        let mut ecs = World::new();
+        ecs.register::<Name>();
+        let mut spell = |name : &str| ecs.create_entity().with(Name{ name: name.to_string() }).build();
+        let (missile, second_missile, fireball) = (spell("Magic Missile"), spell("Magic Missile"), spell("Fireball"));
+        let known = KnownSpells{ spells: vec![missile] };
+
+        let names = ecs.read_storage::<Name>();
+        assert!(known.knows(&names, missile));
+        assert!(known.knows(&names, second_missile));
+        assert!(!known.knows(&names, fireball));
+    }
+
+    #[test]
+    fn encumbrance_slows_past_capacity_and_stops_at_the_hard_limit() {
+        let mut load = Encumbrance{ load: 20.0, capacity: 20.0 };
+        assert!(!load.burdened());
+        assert!(load.can_carry(20.0));
+        assert!(!load.can_carry(20.5));
+
+        load.load = 20.5;
+        assert!(load.burdened());
+    }
+
+    #[test]
+    fn durability_reports_wear_as_a_percentage() {
+        assert_eq!(Durability{ current: 30, max: 40 }.percent(), 75);
+        assert_eq!(Durability{ current: 0, max: 0 }.percent(), 0);
+        assert!(!Durability{ current: 1, max: 40 }.broken());
+        assert!(Durability{ current: 0, max: 40 }.broken());
+    }
+}
@@ -1,17 +1,79 @@
 use specs::prelude::*;
-use super::{CombatStats, SufferDamage, Player, Name, gamelog::GameLog};
+use super::{CombatStats, SufferDamage, Player, Name, DamageType, Resistances, StatusEffects,
+            StatusKind, Slain, Unaware, Morale, RunState, XpValue, Experience, morgue::{self, RunStats}, morale_system,
+            game_events::{self, GameEvents, GameEvent}};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Resistance { Resists, Immune, Weak }
+
+impl std::fmt::Display for Resistance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            Resistance::Resists => "resists",
+            Resistance::Immune => "is immune to",
+            Resistance::Weak => "is weak to"
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// Scales `amount` of `damage_type` damage by the victim's resistances, along with
+/// whichever resistance applied (if any).
+pub fn apply_resistances(amount : i32, damage_type : DamageType, resistances : Option<&Resistances>) -> (i32, Option<Resistance>) {
+    match resistances {
+        Some(r) if r.immune.contains(&damage_type) => (0, Some(Resistance::Immune)),
+        Some(r) if r.weak.contains(&damage_type) => (amount * 2, Some(Resistance::Weak)),
+        Some(r) if r.resist.contains(&damage_type) => (amount / 2, Some(Resistance::Resists)),
+        _ => (amount, None)
+    }
+}
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
-    type SystemData = ( WriteStorage<'a, CombatStats>,
-                        WriteStorage<'a, SufferDamage> );
+    type SystemData = ( Entities<'a>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteStorage<'a, CombatStats>,
+                        WriteStorage<'a, SufferDamage>,
+                        ReadStorage<'a, Resistances>,
+                        ReadStorage<'a, Name>,
+                        WriteStorage<'a, StatusEffects>,
+                        WriteStorage<'a, Slain>,
+                        WriteStorage<'a, Unaware>,
+                        WriteStorage<'a, Morale> );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (entities, mut events, mut stats, mut damage, resistances, names, mut statuses, mut slain, mut unaware, mut morale) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
-            stats.hp -= damage.amount.iter().sum::<i32>();
+        for (entity, stats, damage) in (&entities, &mut stats, &damage).join() {
+            for (amount, damage_type, source) in damage.amount.iter() {
+                let (modified, resistance) = apply_resistances(*amount, *damage_type, resistances.get(entity));
+                if let Some(resistance) = resistance {
+                    events.publish(GameEvent::Resisted{ target: entity, damage_type: *damage_type, amount: *amount, modified, resistance });
+                }
+                let was_alive = stats.hp > 0;
+                stats.hp -= modified;
+                if modified > 0 && stats.hp < stats.max_hp / 3 {
+                    if let Some(morale) = morale.get_mut(entity) {
+                        morale.value -= morale_system::BADLY_HURT_LOSS;
+                    }
+                }
+                if was_alive && stats.hp < 1 {
+                    let cause = match source.and_then(|source| names.get(source)) {
+                        Some(killer) => format!("Killed by {}", killer.name),
+                        None => format!("Succumbed to {} damage", damage_type)
+                    };
+                    slain.insert(entity, Slain{ killer: *source, cause }).expect("Unable to insert slain");
+                }
+            }
+
+            // Getting hurt wakes sleepers up, and gives away whoever did it
+            unaware.remove(entity);
+            if let Some(statuses) = statuses.get_mut(entity) {
+                if statuses.remove(StatusKind::Sleep) {
+                    events.publish(GameEvent::WokeUp{ entity });
+                }
+            }
         }
 
         damage.clear();
@@ -20,31 +82,96 @@ impl<'a> System<'a> for DamageSystem {
 
 pub fn delete_the_dead(ecs : &mut World) {
     let mut dead : Vec<Entity> = Vec::new();
+    let mut player_died = false;
+    let mut player_levelled = false;
     // Using a scope to make the borrow checker happy
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let players = ecs.read_storage::<Player>();
-        let names = ecs.read_storage::<Name>();
+        let slain = ecs.read_storage::<Slain>();
+        let xp_values = ecs.read_storage::<XpValue>();
+        let mut experience = ecs.write_storage::<Experience>();
         let entities = ecs.entities();
-        let mut log = ecs.write_resource::<GameLog>();
+        let player_entity = ecs.fetch::<Entity>();
+        let mut events = ecs.write_resource::<GameEvents>();
+        let mut run_stats = ecs.write_resource::<RunStats>();
         for (entity, stats) in (&entities, &combat_stats).join() {
             if stats.hp < 1 {
                 let player = players.get(entity);
                 match player {
                     None => {
-                        let victim_name = names.get(entity);
-                        if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("{} is dead", &victim_name.name));
+                        let killer = slain.get(entity).and_then(|s| s.killer);
+                        events.publish(GameEvent::EntityDied{ victim: entity, killer });
+                        if let Some(killer) = killer {
+                            if let (Some(xp), Some(killer_exp)) = (xp_values.get(entity), experience.get_mut(killer)) {
+                                killer_exp.xp += xp.xp;
+                                events.publish(GameEvent::ExperienceGained{ entity: killer, amount: xp.xp });
+                                if killer == *player_entity {
+                                    player_levelled |= killer_exp.can_level_up();
+                                }
+                            }
                         }
                         dead.push(entity)
                     }
-                    Some(_) => rltk::console::log("You are dead")
+                    Some(_) => {
+                        if run_stats.cause_of_death.is_none() {
+                            events.publish(GameEvent::EntityDied{ victim: entity, killer: slain.get(entity).and_then(|s| s.killer) });
+                            run_stats.cause_of_death = Some(slain.get(entity).map(|s| s.cause.clone()).unwrap_or_else(|| "Died".to_string()));
+                            player_died = true;
+                        }
+                    }
                 }
             }
         }
     }
 
+    if player_levelled && !player_died {
+        let player_entity = *ecs.fetch::<Entity>();
+        ecs.write_resource::<GameEvents>().publish(GameEvent::LevelUpReady{ entity: player_entity });
+    }
+    // Report everything while the dead are still around to be named
+    game_events::dispatch(ecs);
+
+    if player_died {
+        if let Err(e) = morgue::write_morgue_file(ecs) {
+            rltk::console::log(format!("Unable to write morgue file: {}", e));
+        }
+        *ecs.write_resource::<RunState>() = RunState::GameOver;
+    } else if player_levelled {
+        *ecs.write_resource::<RunState>() = RunState::LevelUp;
+    }
+
+    morale_system::witness_deaths(ecs, &dead);
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fire_resistant() -> Resistances {
+        Resistances{ resist: vec![DamageType::Fire], immune: vec![DamageType::Poison], weak: vec![DamageType::Cold] }
+    }
+
+    #[test]
+    fn unresisted_damage_is_unchanged() {
+        assert_eq!(apply_resistances(7, DamageType::Fire, None), (7, None));
+        assert_eq!(apply_resistances(7, DamageType::Physical, Some(&fire_resistant())), (7, None));
+    }
+
+    #[test]
+    fn resistances_scale_damage() {
+        let r = fire_resistant();
+        assert_eq!(apply_resistances(7, DamageType::Fire, Some(&r)), (3, Some(Resistance::Resists)));
+        assert_eq!(apply_resistances(7, DamageType::Poison, Some(&r)), (0, Some(Resistance::Immune)));
+        assert_eq!(apply_resistances(7, DamageType::Cold, Some(&r)), (14, Some(Resistance::Weak)));
+    }
+
+    #[test]
+    fn immunity_outranks_weakness() {
+        let r = Resistances{ immune: vec![DamageType::Acid], weak: vec![DamageType::Acid], ..Default::default() };
+        assert_eq!(apply_resistances(5, DamageType::Acid, Some(&r)), (0, Some(Resistance::Immune)));
+    }
+}
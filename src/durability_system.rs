@@ -0,0 +1,131 @@
+use specs::prelude::*;
+use rltk::RandomNumberGenerator;
+use super::{Durability, Rusty, Equipped, InBackpack, DefenseBonus, game_events::{GameEvents, GameEvent}};
+
+/// Durability lost by a weapon each time it lands a blow, or armour each time it's hit.
+pub const USE_WEAR : i32 = 1;
+/// Durability eaten away by a single touch of rust.
+pub const RUST_WEAR : i32 = 10;
+
+/// Wear and tear suffered since durability was last updated.
+#[derive(Default)]
+pub struct WearQueue {
+    worn : Vec<(Entity, i32)>,
+    rusted : Vec<Entity>,
+    struck : Vec<Entity>
+}
+
+impl WearQueue {
+    pub fn new() -> WearQueue {
+        WearQueue::default()
+    }
+
+    /// `item` has been used, and loses `amount` durability.
+    pub fn wear(&mut self, item : Entity, amount : i32) {
+        self.worn.push((item, amount));
+    }
+
+    /// `item` has been touched by rust.
+    pub fn corrode(&mut self, item : Entity) {
+        self.rusted.push(item);
+    }
+
+    /// `target` has been hit, which wears down one piece of whatever armour it has on.
+    pub fn strike(&mut self, target : Entity) {
+        self.struck.push(target);
+    }
+}
+
+/// Applies the queued wear to gear, and reports anything that breaks.
+pub struct DurabilitySystem {}
+
+impl<'a> System<'a> for DurabilitySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        WriteExpect<'a, WearQueue>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        WriteStorage<'a, Durability>,
+                        WriteStorage<'a, Rusty>,
+                        ReadStorage<'a, Equipped>,
+                        ReadStorage<'a, InBackpack>,
+                        ReadStorage<'a, DefenseBonus> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, mut queue, mut events, mut rng, mut durability, mut rusty, equipped, backpack, defense_bonuses) = data;
+
+        let mut worn = std::mem::take(&mut queue.worn);
+        for item in std::mem::take(&mut queue.rusted) {
+            rusty.insert(item, Rusty{}).expect("Unable to insert rust");
+            worn.push((item, RUST_WEAR));
+        }
+        for target in std::mem::take(&mut queue.struck) {
+            let armour : Vec<Entity> = (&entities, &equipped, &defense_bonuses, &durability).join()
+                .filter(|(_, equipped, _, durability)| equipped.owner == target && !durability.broken())
+                .map(|(item, _, _, _)| item)
+                .collect();
+            if !armour.is_empty() {
+                let piece = armour[rng.roll_dice(1, armour.len() as i32) as usize - 1];
+                worn.push((piece, USE_WEAR));
+            }
+        }
+
+        for (item, amount) in worn {
+            let Some(durability) = durability.get_mut(item) else { continue };
+            if durability.broken() { continue; }
+            durability.current = i32::max(0, durability.current - amount);
+            if durability.broken() {
+                let owner = equipped.get(item).map(|e| e.owner).or_else(|| backpack.get(item).map(|b| b.owner));
+                if let Some(owner) = owner {
+                    events.publish(GameEvent::ItemBroke{ owner, item });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world_with_sword(current : i32) -> (World, Entity, Entity) {
+        let mut ecs = World::new();
+        ecs.register::<Durability>();
+        ecs.register::<Rusty>();
+        ecs.register::<Equipped>();
+        ecs.register::<InBackpack>();
+        ecs.register::<DefenseBonus>();
+        ecs.insert(WearQueue::new());
+        ecs.insert(GameEvents::default());
+        ecs.insert(RandomNumberGenerator::seeded(1));
+        let owner = ecs.create_entity().build();
+        let sword = ecs.create_entity()
+            .with(Durability{ current, max: 40 })
+            .with(InBackpack{ owner })
+            .build();
+        (ecs, owner, sword)
+    }
+
+    fn durability(ecs : &World, item : Entity) -> i32 {
+        ecs.read_storage::<Durability>().get(item).unwrap().current
+    }
+
+    #[test]
+    fn rust_eats_away_at_gear() {
+        let (ecs, _, sword) = world_with_sword(40);
+        ecs.write_resource::<WearQueue>().corrode(sword);
+        DurabilitySystem{}.run_now(&ecs);
+        assert_eq!(durability(&ecs, sword), 40 - RUST_WEAR);
+        assert!(ecs.read_storage::<Rusty>().get(sword).is_some());
+    }
+
+    #[test]
+    fn gear_breaks_once_and_stays_broken() {
+        let (ecs, owner, sword) = world_with_sword(USE_WEAR);
+        ecs.write_resource::<WearQueue>().wear(sword, USE_WEAR);
+        ecs.write_resource::<WearQueue>().wear(sword, USE_WEAR);
+        DurabilitySystem{}.run_now(&ecs);
+        assert_eq!(durability(&ecs, sword), 0);
+        assert_eq!(ecs.fetch::<GameEvents>().events, vec![GameEvent::ItemBroke{ owner, item: sword }]);
+    }
+}
@@ -0,0 +1,35 @@
+use specs::prelude::*;
+use super::{Encumbrance, Attributes, InBackpack, Weight, Quantity, gamesystem, inventory_system::carrier,
+            game_events::{GameEvents, GameEvent}};
+
+/// Totals up what everyone with an `Encumbrance` is carrying, bags and all, and announces
+/// when that starts or stops slowing them down.
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteStorage<'a, Encumbrance>,
+                        ReadStorage<'a, Attributes>,
+                        ReadStorage<'a, InBackpack>,
+                        ReadStorage<'a, Weight>,
+                        ReadStorage<'a, Quantity> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, mut events, mut encumbrance, attributes, backpack, weights, quantities) = data;
+
+        for (entity, encumbrance, attributes) in (&entities, &mut encumbrance, &attributes).join() {
+            let was_burdened = encumbrance.burdened();
+            encumbrance.capacity = gamesystem::carry_capacity(attributes);
+            encumbrance.load = (&backpack, &weights, quantities.maybe()).join()
+                .filter(|(pack, _, _)| carrier(&backpack, pack.owner) == entity)
+                .map(|(_, weight, quantity)| weight.lbs * quantity.map_or(1, |q| q.amount) as f32)
+                .sum();
+
+            if was_burdened != encumbrance.burdened() {
+                events.publish(GameEvent::BurdenChanged{ entity, burdened: encumbrance.burdened() });
+            }
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use specs::prelude::*;
+use rltk::{Point, RandomNumberGenerator};
+use super::{ForcedMovement, Position, Viewshed, Map, TileType, BlocksTile, SufferDamage, DamageType, Name, CombatStats,
+            game_events::{GameEvents, GameEvent}};
+
+/// Pushes entities with a `ForcedMovement` one tile at a time directly away from its origin.
+/// Running into a wall, anything that blocks the way or another creature stops them short and hurts.
+pub struct ForcedMovementSystem {}
+
+impl<'a> System<'a> for ForcedMovementSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        WriteExpect<'a, Map>,
+                        WriteExpect<'a, Point>,
+                        ReadExpect<'a, Entity>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        WriteStorage<'a, ForcedMovement>,
+                        WriteStorage<'a, Position>,
+                        WriteStorage<'a, Viewshed>,
+                        ReadStorage<'a, BlocksTile>,
+                        WriteStorage<'a, SufferDamage>,
+                        ReadStorage<'a, Name>,
+                        ReadStorage<'a, CombatStats> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, mut map, mut player_pos, player_entity, mut events, mut rng, mut forced, mut positions,
+            mut viewsheds, blockers, mut suffer_damage, names, combat_stats) = data;
+
+        for (entity, push, pos) in (&entities, &forced, &mut positions).join() {
+            let dx = (pos.x - push.origin.x).signum();
+            let dy = (pos.y - push.origin.y).signum();
+            if dx == 0 && dy == 0 { continue; }
+
+            let start = map.xy_index(pos.x, pos.y);
+            let mut moved = 0;
+            // Some(None) is a wall, Some(Some(obstacle)) something standing in the way
+            let mut collision : Option<Option<Entity>> = None;
+            while moved < push.distance {
+                let (x, y) = (pos.x + dx, pos.y + dy);
+                if x < 1 || x > map.width-2 || y < 1 || y > map.height-2 || map.tiles[map.xy_index(x, y)] == TileType::Wall {
+                    collision = Some(None);
+                    break;
+                }
+                let index = map.xy_index(x, y);
+                // The player doesn't block tiles, so check for anyone standing there as well
+                let creature = map.tile_content[index].iter().find(|e| **e != entity && combat_stats.get(**e).is_some()).copied();
+                if map.blocked[index] || creature.is_some() {
+                    collision = Some(creature.or_else(|| map.tile_content[index].iter().find(|e| names.get(**e).is_some()).copied()));
+                    break;
+                }
+                pos.x = x;
+                pos.y = y;
+                moved += 1;
+            }
+
+            if moved > 0 {
+                events.publish(GameEvent::KnockedBack{ entity });
+
+                // Keep the map indexes in step, since nothing re-indexes until the next pass
+                let end = map.xy_index(pos.x, pos.y);
+                map.tile_content[start].retain(|e| *e != entity);
+                map.tile_content[end].push(entity);
+                if blockers.get(entity).is_some() {
+                    map.blocked[start] = false;
+                    map.blocked[end] = true;
+                }
+                if let Some(viewshed) = viewsheds.get_mut(entity) {
+                    viewshed.dirty = true;
+                }
+                if entity == *player_entity {
+                    player_pos.x = pos.x;
+                    player_pos.y = pos.y;
+                }
+            }
+
+            if let Some(obstacle) = collision {
+                events.publish(GameEvent::Slammed{ entity, obstacle });
+                let damage = rng.roll_dice(1, 6);
+                SufferDamage::new_damage(&mut suffer_damage, entity, damage, DamageType::Physical, push.source);
+            }
+        }
+
+        forced.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn creatures_knocked_into_the_player_stop_short() {
+        let mut ecs = World::new();
+        ecs.register::<ForcedMovement>();
+        ecs.register::<Position>();
+        ecs.register::<Viewshed>();
+        ecs.register::<BlocksTile>();
+        ecs.register::<SufferDamage>();
+        ecs.register::<Name>();
+        ecs.register::<CombatStats>();
+        ecs.insert(GameEvents::default());
+        ecs.insert(RandomNumberGenerator::seeded(1));
+        ecs.insert(Point::new(4, 1));
+        let mut map = Map{ tiles: vec![TileType::Floor; 30], width: 10, height: 3, blocked: vec![false; 30],
+            tile_content: vec![Vec::new(); 30], ..Default::default() };
+
+        let player = ecs.create_entity()
+            .with(Name{ name: "Player".to_string() })
+            .with(CombatStats{ max_hp: 10, hp: 10 })
+            .with(Position{ x: 4, y: 1 })
+            .build();
+        let orc = ecs.create_entity()
+            .with(Name{ name: "Orc".to_string() })
+            .with(CombatStats{ max_hp: 10, hp: 10 })
+            .with(BlocksTile{})
+            .with(Position{ x: 2, y: 1 })
+            .with(ForcedMovement{ origin: Point::new(1, 1), distance: 4, source: None })
+            .build();
+        map.tile_content[14].push(player);
+        map.tile_content[12].push(orc);
+        map.blocked[12] = true;
+        ecs.insert(map);
+        ecs.insert(player);
+
+        ForcedMovementSystem{}.run_now(&ecs);
+
+        let positions = ecs.read_storage::<Position>();
+        assert_eq!((positions.get(orc).unwrap().x, positions.get(orc).unwrap().y), (3, 1));
+        assert!(ecs.read_storage::<SufferDamage>().get(orc).is_some());
+        assert!(ecs.fetch::<GameEvents>().events.contains(&GameEvent::Slammed{ entity: orc, obstacle: Some(player) }));
+    }
+}
@@ -0,0 +1,92 @@
+use specs::prelude::*;
+use super::{DamageType, StatusKind, HungerState, gamelog, morgue, identification, damage_system::Resistance,
+            melee_combat_system::AttackResult};
+
+/// Something that happened in the game, published by the systems as it happens and
+/// handed to every subscriber once the systems have run.
+#[derive(PartialEq, Clone, Debug)]
+pub enum GameEvent {
+    Attack{ attacker : Entity, target : Entity, result : AttackResult },
+    /// `projectile` is the ammunition fired or the item thrown.
+    Shot{ shooter : Entity, target : Entity, projectile : Entity, result : AttackResult },
+    OutOfAmmo{ shooter : Entity, weapon : Entity },
+    LandedHarmlessly{ projectile : Entity },
+    /// Damage dealt by using an item, such as a scroll, on a target.
+    Damage{ source : Entity, target : Entity, item : Entity, amount : i32, damage_type : DamageType },
+    /// `item` is `None` for statuses passed on by an attack, such as venom.
+    StatusInflicted{ source : Entity, target : Entity, item : Option<Entity>, kind : StatusKind },
+    /// `amount` of damage was scaled to `modified` by the target's resistances.
+    Resisted{ target : Entity, damage_type : DamageType, amount : i32, modified : i32, resistance : Resistance },
+    StatusExpired{ target : Entity, kind : StatusKind },
+    WokeUp{ entity : Entity },
+    /// An unaware monster has noticed the player.
+    Noticed{ entity : Entity, was_asleep : bool },
+    KnockedBack{ entity : Entity },
+    /// Forced movement ended against `obstacle`, or a wall if it's `None`.
+    Slammed{ entity : Entity, obstacle : Option<Entity> },
+    Fled{ entity : Entity },
+    Rallied{ entity : Entity },
+    Heal{ target : Entity, item : Entity, amount : i32 },
+    Ate{ eater : Entity, item : Entity },
+    HungerChanged{ entity : Entity, state : HungerState },
+    BurdenChanged{ entity : Entity, burdened : bool },
+    SneakToggled{ entity : Entity, sneaking : bool },
+    SwappedPlaces{ entity : Entity, ally : Entity },
+    ItemUsed{ user : Entity, item : Entity },
+    ItemEquipped{ owner : Entity, item : Entity },
+    ItemUnequipped{ owner : Entity, item : Entity },
+    ItemPickedUp{ collector : Entity, item : Entity },
+    ItemDropped{ dropper : Entity, item : Entity },
+    ItemStored{ owner : Entity, item : Entity, container : Entity },
+    ItemRetrieved{ owner : Entity, item : Entity, container : Entity },
+    /// `entity` has worked out what kind of magic item `item` is.
+    ItemIdentified{ entity : Entity, item : Entity },
+    /// A cursed scroll turned on its reader.
+    Backfire{ user : Entity, item : Entity },
+    /// `owner` tried to take off or drop cursed gear.
+    CurseStuck{ owner : Entity, item : Entity },
+    CurseRemoved{ owner : Entity, item : Entity },
+    /// `user` tried to use something with no charges left.
+    NothingHappens{ user : Entity, item : Entity },
+    Recharged{ owner : Entity, item : Entity },
+    /// A corrosive creature's touch rusted `item`, which `target` was using, or found
+    /// nothing metal to rust if it's `None`.
+    CorrosiveTouch{ attacker : Entity, target : Entity, item : Option<Entity> },
+    ItemBroke{ owner : Entity, item : Entity },
+    Repaired{ owner : Entity, item : Entity },
+    EntityDied{ victim : Entity, killer : Option<Entity> },
+    ExperienceGained{ entity : Entity, amount : i32 },
+    SpellCast{ caster : Entity, spell : Entity },
+    SpellLearned{ entity : Entity, spell : Entity },
+    LevelUpReady{ entity : Entity },
+    LevelledUp{ entity : Entity, level : i32 }
+}
+
+/// Events published since subscribers were last notified.
+#[derive(Default)]
+pub struct GameEvents {
+    pub events : Vec<GameEvent>
+}
+
+impl GameEvents {
+    pub fn publish(&mut self, event : GameEvent) {
+        self.events.push(event);
+    }
+}
+
+/// Everything that wants to hear about events. Subscribers run in order, while the
+/// entities the events mention are still alive.
+const SUBSCRIBERS : &[fn(&World, &GameEvent)] = &[
+    gamelog::log_event,
+    morgue::count_event,
+    identification::identify_event
+];
+
+pub fn dispatch(ecs : &World) {
+    let events = std::mem::take(&mut ecs.write_resource::<GameEvents>().events);
+    for event in events.iter() {
+        for subscriber in SUBSCRIBERS.iter() {
+            subscriber(ecs, event);
+        }
+    }
+}
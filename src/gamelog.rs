@@ -1,3 +1,205 @@
+use specs::prelude::*;
+use super::{Name, Quantity, Position, Map, HungerState, identification, game_events::GameEvent, melee_combat_system::AttackResult};
+
 pub struct GameLog {
     pub entries : Vec<String>
 }
+
+fn name_of(ecs : &World, entity : Entity) -> String {
+    identification::display_name(ecs, entity)
+}
+
+/// The item's name, with how many there are if it's a stack of more than one.
+fn stack_name_of(ecs : &World, item : Entity) -> String {
+    match ecs.read_storage::<Quantity>().get(item) {
+        Some(stack) if stack.amount > 1 => format!("{} (x{})", name_of(ecs, item), stack.amount),
+        _ => name_of(ecs, item)
+    }
+}
+
+/// Whether the player can see `entity`, or is it.
+fn in_view(ecs : &World, entity : Entity) -> bool {
+    if entity == *ecs.fetch::<Entity>() { return true; }
+    let map = ecs.fetch::<Map>();
+    ecs.read_storage::<Position>().get(entity).is_some_and(|pos| map.visible_tiles[map.xy_index(pos.x, pos.y)])
+}
+
+/// The player-visible text for an event, if the player should hear about it.
+pub fn describe(ecs : &World, event : &GameEvent) -> Option<String> {
+    let player = *ecs.fetch::<Entity>();
+    let name = |entity| name_of(ecs, entity);
+
+    match event {
+        GameEvent::Attack{ attacker, target, result } => Some(match result {
+            AttackResult::Fumble => format!("{} fumbles the attack on {}!", name(*attacker), name(*target)),
+            AttackResult::Miss => format!("{} misses {}.", name(*attacker), name(*target)),
+            AttackResult::Hit(damage) => format!("{} hits {}, for {} hp.", name(*attacker), name(*target), damage),
+            AttackResult::Critical(damage) => format!("{} critically hits {}, for {} hp!", name(*attacker), name(*target), damage)
+        }),
+        GameEvent::Shot{ shooter, target, projectile, result } => Some(match result {
+            AttackResult::Fumble => format!("{}'s {} goes wildly astray!", name(*shooter), name(*projectile)),
+            AttackResult::Miss => format!("{}'s {} misses {}.", name(*shooter), name(*projectile), name(*target)),
+            AttackResult::Hit(damage) => format!("{}'s {} hits {}, for {} hp.", name(*shooter), name(*projectile), name(*target), damage),
+            AttackResult::Critical(damage) =>
+                format!("{}'s {} critically hits {}, for {} hp!", name(*shooter), name(*projectile), name(*target), damage)
+        }),
+        GameEvent::OutOfAmmo{ shooter, weapon } =>
+            Some(format!("{} has nothing to fire from the {}.", name(*shooter), name(*weapon))),
+        GameEvent::LandedHarmlessly{ projectile } =>
+            Some(format!("The {} lands harmlessly.", name(*projectile))),
+        GameEvent::Damage{ source, target, item, amount, damage_type } if *source == player =>
+            Some(format!("You use {} on {}, inflicting {} {} damage.", name(*item), name(*target), amount, damage_type)),
+        GameEvent::StatusInflicted{ target, item: None, kind, .. } =>
+            Some(format!("{} is {}!", name(*target), kind)),
+        GameEvent::StatusInflicted{ source, target, item: Some(item), kind } if *source == player =>
+            Some(format!("You use {} on {}; they are {}.", name(*item), name(*target), kind)),
+        GameEvent::Resisted{ target, damage_type, amount, modified, resistance } =>
+            Some(format!("{} {} {}: {} damage becomes {}.", name(*target), resistance, damage_type, amount, modified)),
+        GameEvent::StatusExpired{ target, kind } if in_view(ecs, *target) =>
+            Some(format!("{} is no longer {}.", name(*target), kind)),
+        GameEvent::WokeUp{ entity } =>
+            Some(format!("{} wakes up!", name(*entity))),
+        GameEvent::Noticed{ entity, was_asleep } if in_view(ecs, *entity) =>
+            Some(format!("{} {} you!", name(*entity), if *was_asleep { "wakes and notices" } else { "notices" })),
+        GameEvent::KnockedBack{ entity } =>
+            Some(format!("{} is knocked back.", name(*entity))),
+        GameEvent::Slammed{ entity, obstacle } =>
+            Some(format!("{} slams into {}!", name(*entity), obstacle.map_or("the wall".to_string(), name))),
+        GameEvent::Fled{ entity } if in_view(ecs, *entity) =>
+            Some(format!("The {} flees!", name(*entity).to_lowercase())),
+        GameEvent::Rallied{ entity } if in_view(ecs, *entity) =>
+            Some(format!("The {} regains its courage!", name(*entity).to_lowercase())),
+        GameEvent::Heal{ target, item, amount } if *target == player =>
+            Some(format!("You use the {}, healing {} hp.", name(*item), amount)),
+        GameEvent::Ate{ eater, item } if *eater == player =>
+            Some(format!("You eat the {}.", name(*item))),
+        GameEvent::HungerChanged{ entity, state } if *entity == player => match state {
+            HungerState::Normal => Some("You are no longer well fed.".to_string()),
+            HungerState::Hungry => Some("You are hungry.".to_string()),
+            HungerState::Starving => Some("You are starving!".to_string()),
+            HungerState::WellFed => None
+        },
+        GameEvent::BurdenChanged{ entity, burdened } if *entity == player =>
+            Some(if *burdened { "You are burdened by all you carry." } else { "You are no longer burdened." }.to_string()),
+        GameEvent::SneakToggled{ entity, sneaking } if *entity == player =>
+            Some(if *sneaking { "You start sneaking." } else { "You stop sneaking." }.to_string()),
+        GameEvent::SwappedPlaces{ entity, ally } if *entity == player =>
+            Some(format!("You swap places with {}.", name(*ally))),
+        GameEvent::ItemUsed{ user, item } if *user == player =>
+            Some(format!("You use the {}.", name(*item))),
+        GameEvent::ItemEquipped{ owner, item } if *owner == player =>
+            Some(format!("You equip {}.", name(*item))),
+        GameEvent::ItemUnequipped{ owner, item } if *owner == player =>
+            Some(format!("You unequip {}.", name(*item))),
+        GameEvent::ItemPickedUp{ collector, item } if *collector == player =>
+            Some(format!("You pick up the {}.", name(*item))),
+        GameEvent::ItemDropped{ dropper, item } if *dropper == player =>
+            Some(format!("You drop the {}.", stack_name_of(ecs, *item))),
+        GameEvent::ItemStored{ owner, item, container } if *owner == player =>
+            Some(format!("You put the {} in the {}.", stack_name_of(ecs, *item), name(*container))),
+        GameEvent::ItemRetrieved{ owner, item, container } if *owner == player =>
+            Some(format!("You take the {} out of the {}.", stack_name_of(ecs, *item), name(*container))),
+        GameEvent::ItemIdentified{ entity, item } if *entity == player =>
+            Some(format!("You identify the {}: it is a {}.", name(*item),
+                ecs.read_storage::<Name>().get(*item).map_or("mystery", |n| n.name.as_str()))),
+        GameEvent::Backfire{ user, item } if *user == player =>
+            Some(format!("The {} backfires!", name(*item))),
+        GameEvent::CurseStuck{ owner, item } if *owner == player =>
+            Some(format!("You can't let go of the {}; it is cursed!", name(*item))),
+        GameEvent::CurseRemoved{ owner, item } if *owner == player =>
+            Some(format!("The {} glows with a soft white light.", name(*item))),
+        GameEvent::NothingHappens{ user, item } if *user == player =>
+            Some(format!("You use the {}, but nothing happens.", name(*item))),
+        GameEvent::Recharged{ owner, item } if *owner == player =>
+            Some(format!("The {} hums with power.", name(*item))),
+        GameEvent::CorrosiveTouch{ attacker, target, item: Some(item) } if *target == player =>
+            Some(format!("Your {} rusts at the touch of the {}!", name(*item), name(*attacker))),
+        GameEvent::CorrosiveTouch{ attacker, target, item: None } if *target == player =>
+            Some(format!("The {} finds nothing of yours to rust.", name(*attacker))),
+        GameEvent::ItemBroke{ owner, item } if *owner == player =>
+            Some(format!("Your {} breaks!", name(*item))),
+        GameEvent::Repaired{ owner, item } if *owner == player =>
+            Some(format!("You repair the {}.", name(*item))),
+        GameEvent::EntityDied{ victim, .. } if *victim == player =>
+            Some("You are dead!".to_string()),
+        GameEvent::EntityDied{ victim, .. } =>
+            Some(format!("{} is dead", name(*victim))),
+        GameEvent::ExperienceGained{ entity, amount } if *entity == player =>
+            Some(format!("You gain {} experience.", amount)),
+        GameEvent::SpellCast{ caster, spell } if *caster == player =>
+            Some(format!("You cast {}.", name(*spell))),
+        GameEvent::SpellCast{ caster, spell } =>
+            Some(format!("{} casts {}.", name(*caster), name(*spell))),
+        GameEvent::SpellLearned{ entity, spell } if *entity == player =>
+            Some(format!("You learn {}.", name(*spell))),
+        GameEvent::LevelUpReady{ entity } if *entity == player =>
+            Some("You feel more experienced!".to_string()),
+        GameEvent::LevelledUp{ entity, level } if *entity == player =>
+            Some(format!("Welcome to level {}!", level)),
+        _ => None
+    }
+}
+
+/// Subscriber that writes events into the message log.
+pub fn log_event(ecs : &World, event : &GameEvent) {
+    if let Some(text) = describe(ecs, event) {
+        ecs.write_resource::<GameLog>().entries.push(text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ObfuscatedName;
+    use identification::ItemIdentities;
+
+    fn world_with(names : &[&str]) -> (World, Vec<Entity>) {
+        let mut ecs = World::new();
+        ecs.register::<Name>();
+        ecs.register::<ObfuscatedName>();
+        ecs.insert(ItemIdentities::default());
+        let entities : Vec<Entity> = names.iter()
+            .map(|name| ecs.create_entity().with(Name{ name: name.to_string() }).build())
+            .collect();
+        ecs.insert(entities[0]);
+        (ecs, entities)
+    }
+
+    #[test]
+    fn attacks_are_described_for_everyone() {
+        let (ecs, e) = world_with(&["Player", "Orc", "Goblin"]);
+        let event = GameEvent::Attack{ attacker: e[1], target: e[2], result: AttackResult::Hit(4) };
+        assert_eq!(describe(&ecs, &event), Some("Orc hits Goblin, for 4 hp.".to_string()));
+    }
+
+    #[test]
+    fn item_events_are_only_described_for_the_player() {
+        let (ecs, e) = world_with(&["Player", "Orc", "Rations"]);
+        assert_eq!(describe(&ecs, &GameEvent::Ate{ eater: e[0], item: e[2] }), Some("You eat the Rations.".to_string()));
+        assert_eq!(describe(&ecs, &GameEvent::Ate{ eater: e[1], item: e[2] }), None);
+    }
+
+    #[test]
+    fn unidentified_items_go_by_their_disguise() {
+        let (ecs, e) = world_with(&["Player", "Health Potion"]);
+        ecs.write_storage::<ObfuscatedName>().insert(e[1], ObfuscatedName{ name: "Murky Potion".to_string() }).unwrap();
+        let event = GameEvent::ItemIdentified{ entity: e[0], item: e[1] };
+        assert_eq!(describe(&ecs, &event), Some("You identify the Murky Potion: it is a Health Potion.".to_string()));
+        identification::identify_event(&ecs, &event);
+        assert_eq!(describe(&ecs, &GameEvent::Ate{ eater: e[0], item: e[1] }), Some("You eat the Health Potion.".to_string()));
+    }
+
+    #[test]
+    fn monsters_out_of_sight_go_unreported() {
+        let (mut ecs, e) = world_with(&["Player", "Orc", "Goblin"]);
+        ecs.register::<Position>();
+        let mut map = Map{ width: 10, height: 1, visible_tiles: vec![false; 10], ..Default::default() };
+        map.visible_tiles[2] = true;
+        ecs.insert(map);
+        ecs.write_storage::<Position>().insert(e[1], Position{ x: 2, y: 0 }).unwrap();
+        ecs.write_storage::<Position>().insert(e[2], Position{ x: 7, y: 0 }).unwrap();
+
+        assert_eq!(describe(&ecs, &GameEvent::Fled{ entity: e[1] }), Some("The orc flees!".to_string()));
+        assert_eq!(describe(&ecs, &GameEvent::Fled{ entity: e[2] }), None);
+    }
+}
@@ -0,0 +1,59 @@
+use super::{Attributes, Skills};
+
+/// Armour class of a target with no defense at all.
+pub const BASE_ARMOUR_CLASS : i32 = 10;
+
+/// Modifier granted by an attribute: +1 for every two points above 10, and -1 at 9 or 8, -2 at 7 or 6, and so on.
+pub fn attr_bonus(value : i32) -> i32 {
+    (value - 10).div_euclid(2)
+}
+
+/// Maximum hit points, from Fitness and experience level.
+pub fn max_hp(attributes : &Attributes, level : i32) -> i32 {
+    i32::max(1, attributes.fitness * 2 + level * (4 + attr_bonus(attributes.fitness)))
+}
+
+/// Added to the d20 when swinging a melee weapon.
+pub fn melee_attack_bonus(attributes : &Attributes, skills : &Skills) -> i32 {
+    skills.melee + attr_bonus(attributes.might)
+}
+
+/// Added to the d20 when firing or throwing.
+pub fn ranged_attack_bonus(attributes : &Attributes, skills : &Skills) -> i32 {
+    skills.melee + attr_bonus(attributes.quickness)
+}
+
+/// Added to melee damage rolls.
+pub fn melee_damage_bonus(attributes : &Attributes) -> i32 {
+    attr_bonus(attributes.might)
+}
+
+/// Armour class before any worn gear.
+pub fn armour_class(attributes : &Attributes, skills : &Skills) -> i32 {
+    BASE_ARMOUR_CLASS + skills.defense + attr_bonus(attributes.quickness)
+}
+
+/// Extra damage (and turns of any status) when reading scrolls and the like.
+pub fn spell_power(attributes : &Attributes, skills : &Skills) -> i32 {
+    skills.magic + attr_bonus(attributes.intelligence)
+}
+
+/// Maximum mana, from Intelligence, magic skill and experience level.
+pub fn max_mana(attributes : &Attributes, skills : &Skills, level : i32) -> i32 {
+    i32::max(0, 4 + attr_bonus(attributes.intelligence) * 2 + skills.magic * 3 + level)
+}
+
+/// Pounds that can be carried without being slowed down, from Might.
+pub fn carry_capacity(attributes : &Attributes) -> f32 {
+    (15 + attributes.might * 2) as f32
+}
+
+/// Added to the difficulty of noticing this creature.
+pub fn stealth(attributes : &Attributes, skills : &Skills) -> i32 {
+    skills.stealth + attr_bonus(attributes.quickness)
+}
+
+/// Added to the d20 when trying to notice something.
+pub fn perception(attributes : &Attributes) -> i32 {
+    attr_bonus(attributes.intelligence)
+}
@@ -1,45 +1,166 @@
 use rltk::{ RGB, Rltk, Point, VirtualKeyCode };
 use specs::prelude::*;
 use super::{CombatStats, Player, gamelog::GameLog, Map, Name, Position, State, InBackpack,
-            Viewshed};
+            Viewshed, Equipped, EquipmentSlot, MeleePowerBonus, DefenseBonus, DamageDice,
+            Throwable, StatusEffects, StatusKind, Experience, player::LevelUpChoice, morgue::{RunStats, MORGUE_FILE}, initiative_system::GameClock,
+            Attributes, Skills, HungerClock, HungerState, Sneaking, Unaware, Mana, KnownSpells, Spell, Follower, Quantity, Encumbrance, Cursed, Charges, Durability, Rusty, Container, identification, inventory_system, gamesystem, melee_combat_system::UNARMED};
 
 pub fn draw_ui(ecs: &World, context : &mut Rltk) {
     context.draw_box(0, 43, 79, 6, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
 
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
-    for (_player, stats) in (&players, &combat_stats).join() {
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+    let melee_power_bonuses = ecs.read_storage::<MeleePowerBonus>();
+    let defense_bonuses = ecs.read_storage::<DefenseBonus>();
+    let damage_dice = ecs.read_storage::<DamageDice>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let experience = ecs.read_storage::<Experience>();
+    let attributes = ecs.read_storage::<Attributes>();
+    let skills = ecs.read_storage::<Skills>();
+    let hunger_clocks = ecs.read_storage::<HungerClock>();
+    let sneaking = ecs.read_storage::<Sneaking>();
+    let mana = ecs.read_storage::<Mana>();
+    let encumbrance = ecs.read_storage::<Encumbrance>();
+    let durability = ecs.read_storage::<Durability>();
+    for (player_entity, _player, stats) in (&entities, &players, &combat_stats).join() {
         let health = format!(" HP: {} / {} ", stats.hp, stats.max_hp);
-        context.print_color(12, 43, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), &health);
+        context.print_color(2, 43, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), &health);
 
-        context.draw_bar_horizontal(28, 43, 51, stats.hp, stats.max_hp, RGB::named(rltk::RED), RGB::named(rltk::BLACK));
+        context.draw_bar_horizontal(17, 43, 12, stats.hp, stats.max_hp, RGB::named(rltk::RED), RGB::named(rltk::BLACK));
+
+        if let Some(pool) = mana.get(player_entity) {
+            let mana_text = format!(" MP: {} / {} ", pool.current, pool.max);
+            context.print_color(30, 43, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), &mana_text);
+        }
+
+        if let Some(exp) = experience.get(player_entity) {
+            let level = format!(" Level {} ", exp.level);
+            context.print_color(44, 43, RGB::named(rltk::GOLD), RGB::named(rltk::BLACK), &level);
+            context.draw_bar_horizontal(54, 43, 24, exp.xp, exp.xp_to_next_level(), RGB::named(rltk::GOLD), RGB::named(rltk::BLACK));
+        }
+
+        // Stats line, including whatever the player has equipped
+        let player_attributes = attributes.get(player_entity).cloned().unwrap_or_default();
+        let player_skills = skills.get(player_entity).cloned().unwrap_or_default();
+        let mut to_hit = gamesystem::melee_attack_bonus(&player_attributes, &player_skills);
+        let mut armour_class = gamesystem::armour_class(&player_attributes, &player_skills);
+        let mut dice = *damage_dice.get(player_entity).unwrap_or(&UNARMED);
+        for (item_entity, equipped_by, _) in (&entities, &equipped, durability.maybe()).join()
+            .filter(|(_, equipped, durability)| equipped.owner == player_entity && !durability.is_some_and(|d| d.broken())) {
+            if let Some(bonus) = melee_power_bonuses.get(item_entity) { to_hit += bonus.power; }
+            if let Some(bonus) = defense_bonuses.get(item_entity) { armour_class += bonus.defense; }
+            if equipped_by.slot == EquipmentSlot::Melee {
+                if let Some(weapon_dice) = damage_dice.get(item_entity) { dice = *weapon_dice; }
+            }
+        }
+        dice.bonus += gamesystem::melee_damage_bonus(&player_attributes);
+        let stats_line = format!(" Mig {} Fit {} Qui {} Int {}  To-hit: {:+}  AC: {}  Dmg: {} ",
+            player_attributes.might, player_attributes.fitness, player_attributes.quickness, player_attributes.intelligence,
+            to_hit, armour_class, dice);
+        context.print_color(2, 44, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), &stats_line);
+
+        // Hunger and then any active status effects, after the stats line, for as many as fit inside the box
+        let mut labels : Vec<(String, RGB)> = Vec::new();
+        if let Some(hunger) = hunger_clocks.get(player_entity) {
+            if hunger.state != HungerState::Normal {
+                labels.push((format!(" {} ", hunger.state), hunger_colour(hunger.state)));
+            }
+        }
+        if sneaking.get(player_entity).is_some() {
+            labels.push((" Sneaking ".to_string(), RGB::named(rltk::GREY)));
+        }
+        if encumbrance.get(player_entity).is_some_and(|e| e.burdened()) {
+            labels.push((" Burdened ".to_string(), RGB::named(rltk::ORANGE)));
+        }
+        if let Some(player_statuses) = statuses.get(player_entity) {
+            for effect in player_statuses.effects.iter() {
+                labels.push((format!(" {} ({}) ", effect.kind, effect.turns), status_colour(effect.kind)));
+            }
+        }
+        let mut x = 3 + stats_line.len() as i32;
+        for (label, colour) in labels.iter() {
+            if x + label.len() as i32 > 79 { break; }
+            context.print_color(x, 44, *colour, RGB::named(rltk::BLACK), label);
+            x += label.len() as i32;
+        }
     }
 
     let log = ecs.fetch::<GameLog>();
-    let mut y = 44;
-    for s in log.entries.iter().rev() {
-        if y < 49 { context.print(2, y, s); }
-        y += 1;
+    for (y, s) in (45..49).zip(log.entries.iter().rev()) {
+        context.print(2, y, s);
     }
 
+    draw_allies(ecs, context);
+
     // Draw mouse cursor
     let mouse_pos = context.mouse_pos();
     context.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::MAGENTA));
     draw_tooltips(ecs, context);
 }
 
+/// Sidebar in the top right corner listing the player's allies and their health.
+fn draw_allies(ecs : &World, context : &mut Rltk) {
+    let player_entity = ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let followers = ecs.read_storage::<Follower>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+
+    let allies : Vec<(&Name, &CombatStats)> = (&followers, &names, &combat_stats).join()
+        .filter(|(follower, _, _)| follower.leader == *player_entity)
+        .map(|(_, name, stats)| (name, stats))
+        .collect();
+    if allies.is_empty() { return; }
+
+    context.draw_box(58, 0, 21, allies.len() as i32 + 1, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    context.print_color(60, 0, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Allies");
+    for (y, (name, stats)) in (1..).zip(allies.iter()) {
+        context.print(60, y, &name.name);
+        context.draw_bar_horizontal(70, y, 8, stats.hp, stats.max_hp, RGB::named(rltk::RED), RGB::named(rltk::BLACK));
+    }
+}
+
+fn status_colour(kind : StatusKind) -> RGB {
+    match kind {
+        StatusKind::Poison => RGB::named(rltk::GREEN),
+        StatusKind::Burning => RGB::named(rltk::ORANGE),
+        StatusKind::Sleep => RGB::named(rltk::BLUE),
+        StatusKind::Slow => RGB::named(rltk::GREY),
+        StatusKind::Haste => RGB::named(rltk::YELLOW),
+        StatusKind::Paralysis => RGB::named(rltk::WHITE),
+        StatusKind::Confusion => RGB::named(rltk::PINK),
+        StatusKind::Regeneration => RGB::named(rltk::LIGHT_GREEN)
+    }
+}
+
+fn hunger_colour(state : HungerState) -> RGB {
+    match state {
+        HungerState::WellFed => RGB::named(rltk::GREEN),
+        HungerState::Normal => RGB::named(rltk::WHITE),
+        HungerState::Hungry => RGB::named(rltk::ORANGE),
+        HungerState::Starving => RGB::named(rltk::RED)
+    }
+}
+
 fn draw_tooltips(ecs: &World, context : &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let unaware = ecs.read_storage::<Unaware>();
 
     let mouse_pos = context.mouse_pos();
     if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height { return; }
     let mut tooltip : Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
+    for (entity, _name, position, unaware) in (&ecs.entities(), &names, &positions, unaware.maybe()).join() {
         let index = map.xy_index(position.x, position.y);
         if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[index] {
-            tooltip.push(name.name.to_string());
+            let name = item_label(ecs, entity);
+            match unaware {
+                None => tooltip.push(name),
+                Some(unaware) if unaware.asleep => tooltip.push(format!("{} (asleep)", name)),
+                Some(_) => tooltip.push(format!("{} (unaware)", name))
+            }
         }
     }
 
@@ -53,29 +174,25 @@ fn draw_tooltips(ecs: &World, context : &mut Rltk) {
         if mouse_pos.0 > 40 {
             let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
             let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                 context.print_color(left_x, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
                 let padding = (width - s.len() as i32)-1;
                 for i in 0..padding {
-                    context.print_color(arrow_pos.x - i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
+                    context.print_color(arrow_pos.x - i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), " ");
                 }
-                y += 1;
             }
-            context.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"->".to_string());
+            context.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), "->");
         } else {
             let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
             let left_x = mouse_pos.0 +3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                 context.print_color(left_x + 1, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
                 let padding = (width - s.len() as i32)-1;
                 for i in 0..padding {
-                    context.print_color(arrow_pos.x + 1 + i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
+                    context.print_color(arrow_pos.x + 1 + i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), " ");
                 }
-                y += 1;
             }
-            context.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"<-".to_string());
+            context.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), "<-");
         }
     }
 }
@@ -83,31 +200,22 @@ fn draw_tooltips(ecs: &World, context : &mut Rltk) {
 #[derive(PartialEq, Copy, Clone)]
 pub enum ItemMenuResult { Cancel, NoResponse, Selected }
 
-pub fn show_inventory(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_entity = gamestate.ecs.fetch::<Entity>();
-    let names = gamestate.ecs.read_storage::<Name>();
-    let backpack = gamestate.ecs.read_storage::<InBackpack>();
-    let entities = gamestate.ecs.entities();
-
-    let inventory = (&backpack, &names).join().filter(|item| item.0.owner == *player_entity );
-    let count = inventory.count();
+/// Draws a lettered menu of items and returns the entity the player picks, if any.
+fn item_menu(context : &mut Rltk, title : &str, items : &[(Entity, String)]) -> (ItemMenuResult, Option<Entity>) {
+    let count = items.len();
 
-    let mut y = (25 - (count / 2)) as i32;
-    context.draw_box(15, y-2, 31, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
-    context.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Inventory");
+    let y = (25 - (count / 2)) as i32;
+    context.draw_box(15, y-2, 40, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    context.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), title);
     context.print_color(18, y+count as i32+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
 
-    let mut equippable : Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity ) {
+    for (j, (_entity, label)) in items.iter().enumerate() {
+        let y = y + j as i32;
         context.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
         context.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
         context.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
 
-        context.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        j += 1;
+        context.print(21, y, label);
     }
 
     match context.key {
@@ -118,7 +226,7 @@ pub fn show_inventory(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuR
                 _ => {
                     let selection = rltk::letter_to_option(key);
                     if selection > -1 && selection < count as i32 {
-                        return (ItemMenuResult::Selected, Some(equippable[selection as usize]));
+                        return (ItemMenuResult::Selected, Some(items[selection as usize].0));
                     }
                     (ItemMenuResult::NoResponse, None)
                 }
@@ -127,50 +235,167 @@ pub fn show_inventory(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuR
     }
 }
 
+/// The name shown for an item in menus and tooltips, with its condition and any curse the
+/// player knows about, e.g. "Rusty Longsword (43%)".
+fn item_label(ecs : &World, item : Entity) -> String {
+    let mut label = identification::display_name(ecs, item);
+    if ecs.read_storage::<Rusty>().get(item).is_some() {
+        label = format!("Rusty {}", label);
+    }
+    if identification::curse_known(ecs, item) {
+        label = format!("{} (cursed)", label);
+    }
+    match ecs.read_storage::<Durability>().get(item) {
+        Some(durability) if durability.broken() => format!("{} (broken)", label),
+        Some(durability) if durability.current < durability.max => format!("{} ({}%)", label, durability.percent()),
+        _ => label
+    }
+}
+
+/// Everything the player is carrying, labelled with how many there are, any charges left
+/// and where it is worn if equipped. Whatever is in their containers isn't included.
+fn player_items(ecs : &World) -> Vec<(Entity, String)> {
+    items_held_by(ecs, *ecs.fetch::<Entity>())
+}
+
+/// The items directly held by `owner`, which may be a creature or a container.
+fn items_held_by(ecs : &World, owner : Entity) -> Vec<(Entity, String)> {
+    let names = ecs.read_storage::<Name>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let quantities = ecs.read_storage::<Quantity>();
+    let charges = ecs.read_storage::<Charges>();
+    let entities = ecs.entities();
+
+    (&entities, &backpack, &names, quantities.maybe()).join()
+        .filter(|item| item.1.owner == owner )
+        .map(|(entity, _pack, _name, quantity)| {
+            let name = item_label(ecs, entity);
+            let mut label = match quantity {
+                Some(quantity) if quantity.amount > 1 => format!("{} (x{})", name, quantity.amount),
+                _ => name
+            };
+            if let Some(charges) = charges.get(entity) {
+                label = format!("{} [{}/{}]", label, charges.current, charges.max);
+            }
+            match equipped.get(entity) {
+                None => (entity, label),
+                Some(equipped) => (entity, format!("{} ({})", label, equipped.slot))
+            }
+        })
+        .collect()
+}
+
+pub fn show_inventory(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let items = player_items(&gamestate.ecs);
+    let encumbrance = gamestate.ecs.read_storage::<Encumbrance>();
+    let title = match encumbrance.get(*gamestate.ecs.fetch::<Entity>()) {
+        None => "Inventory".to_string(),
+        Some(load) => format!("Inventory ({:.1} / {:.0} lbs{})", load.load, load.capacity, if load.burdened() { ", burdened" } else { "" })
+    };
+    item_menu(context, &title, &items)
+}
+
 pub fn drop_item_menu(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_entity = gamestate.ecs.fetch::<Entity>();
-    let names = gamestate.ecs.read_storage::<Name>();
-    let backpack = gamestate.ecs.read_storage::<InBackpack>();
-    let entities = gamestate.ecs.entities();
+    let items = player_items(&gamestate.ecs);
+    item_menu(context, "Drop Which Item?", &items)
+}
 
-    let inventory = (&backpack, &names).join().filter(|item| item.0.owner == *player_entity );
-    let count = inventory.count();
+/// Lists what's in a bag; picking something takes it out.
+pub fn show_bag(gamestate : &mut State, context : &mut Rltk, bag : Entity) -> (ItemMenuResult, Option<Entity>) {
+    let ecs = &gamestate.ecs;
+    let items = items_held_by(ecs, bag);
+    let max_weight = ecs.read_storage::<Container>().get(bag).map_or(0.0, |c| c.max_weight);
+    let title = format!("Take from {} ({:.1} / {:.0} lbs)", identification::display_name(ecs, bag),
+        inventory_system::contents_weight(ecs, bag), max_weight);
+    item_menu(context, &title, &items)
+}
 
-    let mut y = (25 - (count / 2)) as i32;
-    context.draw_box(15, y-2, 31, (count+3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
-    context.print_color(18, y-2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Drop Which Item?");
-    context.print_color(18, y+count as i32+1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+/// The containers the player is carrying.
+pub fn player_containers(ecs : &World) -> Vec<(Entity, String)> {
+    let containers = ecs.read_storage::<Container>();
+    let mut items = player_items(ecs);
+    items.retain(|(entity, _)| containers.get(*entity).is_some());
+    items
+}
 
-    let mut equippable : Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names).join().filter(|item| item.1.owner == *player_entity ) {
-        context.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
-        context.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
-        context.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+pub fn put_away_menu(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let containers = gamestate.ecs.read_storage::<Container>();
+    let equipped = gamestate.ecs.read_storage::<Equipped>();
+    let mut items = player_items(&gamestate.ecs);
+    items.retain(|(entity, _)| containers.get(*entity).is_none() && equipped.get(*entity).is_none());
+    item_menu(context, "Put Away Which Item?", &items)
+}
 
-        context.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        j += 1;
-    }
+pub fn choose_container_menu(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let items = player_containers(&gamestate.ecs);
+    item_menu(context, "Put It In Which Container?", &items)
+}
+
+/// Asks how many of a stack to drop. The number typed so far is passed back in with
+/// `NoResponse`; confirming with nothing typed drops the whole stack.
+pub fn drop_quantity_prompt(gamestate : &mut State, context : &mut Rltk, item : Entity, amount : i32) -> (ItemMenuResult, i32) {
+    let quantities = gamestate.ecs.read_storage::<Quantity>();
+    let stack_size = quantities.get(item).map_or(1, |q| q.amount);
+    let name = identification::display_name(&gamestate.ecs, item);
+
+    context.draw_box(15, 22, 40, 4, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    context.print_color(18, 22, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), format!("Drop how many {}? (1-{})", name, stack_size));
+    let typed = if amount > 0 { amount.to_string() } else { String::new() };
+    context.print(18, 24, format!("> {}_", typed));
+    context.print_color(18, 26, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ENTER for all, ESCAPE to cancel");
 
     match context.key {
-        None => (ItemMenuResult::NoResponse, None),
-        Some(key) => {
-            match key {
-                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
-                _ => {
-                    let selection = rltk::letter_to_option(key);
-                    if selection > -1 && selection < count as i32 {
-                        return (ItemMenuResult::Selected, Some(equippable[selection as usize]));
-                    }
-                    (ItemMenuResult::NoResponse, None)
-                }
-            }
+        None => (ItemMenuResult::NoResponse, amount),
+        Some(VirtualKeyCode::Escape) => (ItemMenuResult::Cancel, 0),
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::NumpadEnter) => (ItemMenuResult::Selected, amount),
+        Some(VirtualKeyCode::Back) => (ItemMenuResult::NoResponse, amount / 10),
+        Some(key) => match digit(key) {
+            Some(digit) => (ItemMenuResult::NoResponse, i32::min(stack_size, amount * 10 + digit)),
+            None => (ItemMenuResult::NoResponse, amount)
         }
     }
 }
 
+fn digit(key : VirtualKeyCode) -> Option<i32> {
+    match key {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(0),
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(1),
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(2),
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some(3),
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some(4),
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some(5),
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some(6),
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some(7),
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some(8),
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some(9),
+        _ => None
+    }
+}
+
+pub fn throw_item_menu(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let throwables = gamestate.ecs.read_storage::<Throwable>();
+    let equipped = gamestate.ecs.read_storage::<Equipped>();
+    let cursed = gamestate.ecs.read_storage::<Cursed>();
+    let mut items = player_items(&gamestate.ecs);
+    // Cursed gear won't leave the hand it's in
+    items.retain(|(entity, _)| throwables.get(*entity).is_some() && (equipped.get(*entity).is_none() || cursed.get(*entity).is_none()));
+    item_menu(context, "Throw Which Item?", &items)
+}
+
+pub fn cast_spell_menu(gamestate : &mut State, context : &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let ecs = &gamestate.ecs;
+    let names = ecs.read_storage::<Name>();
+    let spells = ecs.read_storage::<Spell>();
+    let known_spells = ecs.read_storage::<KnownSpells>();
+    let spells : Vec<(Entity, String)> = known_spells.get(*ecs.fetch::<Entity>())
+        .map(|known| known.spells.iter()
+            .map(|spell| (*spell, format!("{} ({} mana)", names.get(*spell).unwrap().name, spells.get(*spell).unwrap().mana_cost)))
+            .collect())
+        .unwrap_or_default();
+    item_menu(context, "Cast Which Spell?", &spells)
+}
+
 pub fn ranged_target(gamestate : &mut State, context : &mut Rltk, range : i32) -> (ItemMenuResult, Option<Point>) {
     let player_entity = gamestate.ecs.fetch::<Entity>();
     let player_pos = gamestate.ecs.fetch::<Point>();
@@ -212,3 +437,66 @@ pub fn ranged_target(gamestate : &mut State, context : &mut Rltk, range : i32) -
 
     (ItemMenuResult::NoResponse, None)
 }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum GameOverResult { NoSelection, NewGame }
+
+pub fn game_over(ecs : &World, context : &mut Rltk) -> GameOverResult {
+    let stats = ecs.fetch::<RunStats>();
+    let clock = ecs.fetch::<GameClock>();
+    let map = ecs.fetch::<Map>();
+
+    context.draw_box(15, 14, 50, 14, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    context.print_color_centered(16, RGB::named(rltk::RED), RGB::named(rltk::BLACK), "Your journey has ended!");
+    if let Some(cause) = &stats.cause_of_death {
+        context.print_color_centered(18, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), format!("{} on level {}.", cause, map.depth));
+    }
+    context.print_color_centered(20, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), format!("You survived {} turns.", clock.turns));
+    context.print_color_centered(21, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), format!("You slew {} monsters.", stats.kills));
+    context.print_color_centered(23, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), format!("A morgue file was written to {}", MORGUE_FILE));
+    context.print_color_centered(26, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Press ENTER to start a new run.");
+
+    match context.key {
+        Some(VirtualKeyCode::Return) => GameOverResult::NewGame,
+        _ => GameOverResult::NoSelection
+    }
+}
+
+pub fn level_up_menu(ecs : &World, context : &mut Rltk) -> Option<LevelUpChoice> {
+    let experience = ecs.read_storage::<Experience>();
+    let level = experience.get(*ecs.fetch::<Entity>()).map(|e| e.level + 1).unwrap_or(1);
+
+    let choices = [
+        (LevelUpChoice::Might, "Might: hit and damage in melee"),
+        (LevelUpChoice::Fitness, "Fitness: more hit points"),
+        (LevelUpChoice::Quickness, "Quickness: harder to hit, better aim"),
+        (LevelUpChoice::Intelligence, "Intelligence: stronger scrolls"),
+        (LevelUpChoice::Melee, "Melee skill"),
+        (LevelUpChoice::Defense, "Defense skill"),
+        (LevelUpChoice::Magic, "Magic skill"),
+        (LevelUpChoice::Stealth, "Stealth skill")
+    ];
+
+    context.draw_box(15, 14, 50, 13, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    context.print_color_centered(15, RGB::named(rltk::GOLD), RGB::named(rltk::BLACK), format!("You have reached level {}!", level));
+    context.print_color_centered(16, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Your health and melee skill increase.");
+    context.print_color_centered(17, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Choose one more thing to improve by 1:");
+    for (j, (_choice, label)) in choices.iter().enumerate() {
+        let y = 19 + j as i32;
+        context.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        context.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), 97+j as rltk::FontCharType);
+        context.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        context.print(21, y, label);
+    }
+
+    match context.key {
+        None => None,
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 && selection < choices.len() as i32 {
+                return Some(choices[selection as usize].0);
+            }
+            None
+        }
+    }
+}
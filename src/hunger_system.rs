@@ -0,0 +1,52 @@
+use specs::prelude::*;
+use super::{HungerClock, HungerState, SufferDamage, DamageType, initiative_system::GameClock,
+            game_events::{GameEvents, GameEvent}};
+
+/// Turns spent in each state before dropping to the next.
+pub const WELL_FED_TURNS : i32 = 20;
+pub const NORMAL_TURNS : i32 = 200;
+pub const HUNGRY_TURNS : i32 = 200;
+
+/// Damage taken each turn while starving.
+const STARVATION_DAMAGE : i32 = 1;
+
+/// Counts down everyone's hunger once per game turn.
+pub struct HungerSystem {}
+
+impl<'a> System<'a> for HungerSystem {
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, GameClock>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteStorage<'a, HungerClock>,
+                        WriteStorage<'a, SufferDamage> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, clock, mut events, mut hunger_clocks, mut suffer_damage) = data;
+
+        if !clock.ticked { return; }
+
+        for (entity, hunger) in (&entities, &mut hunger_clocks).join() {
+            if hunger.state == HungerState::Starving {
+                SufferDamage::new_damage(&mut suffer_damage, entity, STARVATION_DAMAGE, DamageType::Physical, None);
+                continue;
+            }
+
+            hunger.duration -= 1;
+            if hunger.duration > 0 { continue; }
+
+            match hunger.state {
+                HungerState::WellFed => {
+                    hunger.state = HungerState::Normal;
+                    hunger.duration = NORMAL_TURNS;
+                }
+                HungerState::Normal => {
+                    hunger.state = HungerState::Hungry;
+                    hunger.duration = HUNGRY_TURNS;
+                }
+                HungerState::Hungry => hunger.state = HungerState::Starving,
+                HungerState::Starving => {}
+            }
+            events.publish(GameEvent::HungerChanged{ entity, state: hunger.state });
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::{HashMap, HashSet};
+use rltk::{RandomNumberGenerator, RGB};
+use specs::prelude::*;
+use super::{Name, ObfuscatedName, Cursed, Equipped, game_events::GameEvent};
+
+const SCROLL_SYLLABLES : &[&str] = &["ka", "zor", "el", "xy", "zzy", "bar", "ul", "om", "nix", "fu", "ra", "tep", "ish", "vo"];
+/// Each potion disguise, and the colour its glyph is drawn in.
+const POTION_COLOURS : &[(&str, (u8, u8, u8))] = &[
+    ("Murky", rltk::DARK_OLIVE), ("Bubbling", rltk::LIGHT_BLUE), ("Golden", rltk::GOLD), ("Crimson", rltk::CRIMSON),
+    ("Smoky", rltk::GREY), ("Violet", rltk::VIOLET), ("Cloudy", rltk::WHITE_SMOKE), ("Fizzy", rltk::LIGHT_GREEN),
+    ("Oily", rltk::DARK_GOLDENROD), ("Glowing", rltk::YELLOW), ("Pink", rltk::PINK), ("Black", rltk::DIM_GREY)
+];
+/// Random scroll labels to try before giving up and numbering them.
+const LABEL_ATTEMPTS : usize = 100;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MagicItemKind { Scroll, Potion }
+
+/// What each kind of magic item looks like this run, and which of them the player has
+/// worked out. Items are keyed by their true name.
+#[derive(Default)]
+pub struct ItemIdentities {
+    disguises : HashMap<String, String>,
+    identified : HashSet<String>
+}
+
+impl ItemIdentities {
+    /// The unidentified name for `true_name`, making one up the first time it's asked for.
+    pub fn disguise(&mut self, rng : &mut RandomNumberGenerator, true_name : &str, kind : MagicItemKind) -> String {
+        if let Some(disguise) = self.disguises.get(true_name) {
+            return disguise.clone();
+        }
+        let unused = |candidate : &String| !self.disguises.values().any(|used| used == candidate);
+        let disguise = match kind {
+            MagicItemKind::Scroll => (0..LABEL_ATTEMPTS)
+                .map(|_| {
+                    let syllables = rng.roll_dice(2, 2) - 1;
+                    let label : String = (0..syllables)
+                        .map(|_| SCROLL_SYLLABLES[rng.roll_dice(1, SCROLL_SYLLABLES.len() as i32) as usize - 1])
+                        .collect();
+                    format!("Scroll labelled {}", label.to_uppercase())
+                })
+                .find(unused),
+            MagicItemKind::Potion => {
+                let colours : Vec<String> = POTION_COLOURS.iter()
+                    .map(|(colour, _)| format!("{} Potion", colour))
+                    .filter(unused)
+                    .collect();
+                if colours.is_empty() { None } else { Some(colours[rng.roll_dice(1, colours.len() as i32) as usize - 1].clone()) }
+            }
+        };
+        // Once the disguises run out, unknown items just get numbered
+        let disguise = disguise.unwrap_or_else(|| match kind {
+            MagicItemKind::Scroll => format!("Unlabelled Scroll #{}", self.disguises.len() + 1),
+            MagicItemKind::Potion => format!("Plain Potion #{}", self.disguises.len() + 1)
+        });
+        self.disguises.insert(true_name.to_string(), disguise.clone());
+        disguise
+    }
+
+    pub fn is_identified(&self, true_name : &str) -> bool {
+        self.identified.contains(true_name)
+    }
+
+    pub fn identify(&mut self, true_name : &str) {
+        self.identified.insert(true_name.to_string());
+    }
+}
+
+/// The colour to draw a magic item going by `disguise`, so its glyph gives no more away
+/// than its name: potions are their colour, and every scroll looks the same.
+pub fn disguise_colour(kind : MagicItemKind, disguise : &str) -> RGB {
+    match kind {
+        MagicItemKind::Scroll => RGB::named(rltk::WHEAT),
+        MagicItemKind::Potion => POTION_COLOURS.iter()
+            .find(|(colour, _)| disguise.starts_with(colour))
+            .map_or(RGB::named(rltk::WHITE), |(_, rgb)| RGB::named(*rgb))
+    }
+}
+
+/// The name the player knows `entity` by: its disguise if it's a magic item they haven't
+/// identified yet, otherwise its true name.
+pub fn display_name(ecs : &World, entity : Entity) -> String {
+    let names = ecs.read_storage::<Name>();
+    let Some(name) = names.get(entity) else { return "Something".to_string() };
+    match ecs.read_storage::<ObfuscatedName>().get(entity) {
+        Some(obfuscated) if !ecs.fetch::<ItemIdentities>().is_identified(&name.name) => obfuscated.name.clone(),
+        _ => name.name.clone()
+    }
+}
+
+/// Whether the player can tell `item` is cursed: it has been equipped, or its kind has
+/// been identified.
+pub fn curse_known(ecs : &World, item : Entity) -> bool {
+    if ecs.read_storage::<Cursed>().get(item).is_none() { return false; }
+    if ecs.read_storage::<Equipped>().get(item).is_some() { return true; }
+    let names = ecs.read_storage::<Name>();
+    ecs.read_storage::<ObfuscatedName>().get(item).is_some()
+        && names.get(item).is_some_and(|name| ecs.fetch::<ItemIdentities>().is_identified(&name.name))
+}
+
+/// Subscriber that remembers what the player has identified. It runs after the log,
+/// so the message can still give the old name.
+pub fn identify_event(ecs : &World, event : &GameEvent) {
+    if let GameEvent::ItemIdentified{ entity, item } = event {
+        if *entity != *ecs.fetch::<Entity>() { return; }
+        if let Some(name) = ecs.read_storage::<Name>().get(*item) {
+            ecs.write_resource::<ItemIdentities>().identify(&name.name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_kind_of_item_gets_its_own_disguise() {
+        let mut identities = ItemIdentities::default();
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let first = identities.disguise(&mut rng, "Health Potion", MagicItemKind::Potion);
+        assert_eq!(identities.disguise(&mut rng, "Health Potion", MagicItemKind::Potion), first);
+        assert_ne!(identities.disguise(&mut rng, "Potion of Haste", MagicItemKind::Potion), first);
+    }
+
+    #[test]
+    fn disguises_stay_unique_once_the_colours_run_out() {
+        let mut identities = ItemIdentities::default();
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let disguises : HashSet<String> = (0..POTION_COLOURS.len() + 5)
+            .map(|n| identities.disguise(&mut rng, &format!("Potion {}", n), MagicItemKind::Potion))
+            .collect();
+        assert_eq!(disguises.len(), POTION_COLOURS.len() + 5);
+    }
+
+    #[test]
+    fn potion_glyphs_match_their_disguise() {
+        assert_eq!(disguise_colour(MagicItemKind::Potion, "Crimson Potion"), RGB::named(rltk::CRIMSON));
+        assert_eq!(disguise_colour(MagicItemKind::Scroll, "Scroll labelled ZOR"),
+            disguise_colour(MagicItemKind::Scroll, "Scroll labelled KAFU"));
+    }
+}
@@ -0,0 +1,109 @@
+use specs::prelude::*;
+use super::{Initiative, ActionType, Player, Monster, Follower, RunState, StatusEffects, StatusKind, Encumbrance};
+
+/// Energy an entity needs banked before it can take an action.
+pub const ACTION_THRESHOLD : i32 = 100;
+
+/// Speed of an ordinary creature: one action per turn.
+pub const NORMAL_SPEED : i32 = 100;
+
+/// Counts game turns. `ticked` is set for the pass of systems in which a new turn began,
+/// so per-turn effects only happen once however many actions are taken in it.
+#[derive(Default)]
+pub struct GameClock {
+    pub turns : i32,
+    pub ticked : bool
+}
+
+/// Haste doubles speed and slow halves it. Carrying too much costs a quarter.
+pub fn effective_speed(speed : i32, statuses : Option<&StatusEffects>, encumbrance : Option<&Encumbrance>) -> i32 {
+    let mut speed = speed;
+    if let Some(statuses) = statuses {
+        if statuses.has(StatusKind::Haste) { speed *= 2; }
+        if statuses.has(StatusKind::Slow) { speed /= 2; }
+    }
+    if encumbrance.is_some_and(|e| e.burdened()) { speed = speed * 3 / 4; }
+    speed
+}
+
+pub fn player_ready(ecs : &World) -> bool {
+    let initiatives = ecs.read_storage::<Initiative>();
+    initiatives.get(*ecs.fetch::<Entity>()).is_some_and(|i| i.energy >= ACTION_THRESHOLD)
+}
+
+/// True if any monster or follower still has an action to take this turn.
+pub fn others_ready(ecs : &World) -> bool {
+    let initiatives = ecs.read_storage::<Initiative>();
+    let monsters = ecs.read_storage::<Monster>();
+    let followers = ecs.read_storage::<Follower>();
+    (&initiatives, monsters.mask() | followers.mask()).join().any(|(i, _)| i.energy >= ACTION_THRESHOLD)
+}
+
+pub fn spend_player_energy(ecs : &World, action : ActionType) {
+    let mut initiatives = ecs.write_storage::<Initiative>();
+    if let Some(initiative) = initiatives.get_mut(*ecs.fetch::<Entity>()) {
+        initiative.spend(action);
+    }
+}
+
+/// While time is passing, starts a new turn whenever nobody has an action left to take,
+/// handing everyone energy according to their speed. Only the player, monsters and
+/// followers ever spend energy, so nothing else can hold the next turn up.
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( ReadExpect<'a, RunState>,
+                        WriteExpect<'a, GameClock>,
+                        WriteStorage<'a, Initiative>,
+                        ReadStorage<'a, StatusEffects>,
+                        ReadStorage<'a, Encumbrance>,
+                        ReadStorage<'a, Player>,
+                        ReadStorage<'a, Monster>,
+                        ReadStorage<'a, Follower> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (runstate, mut clock, mut initiatives, statuses, encumbrance, players, monsters, followers) = data;
+
+        clock.ticked = false;
+        if *runstate != RunState::Ticking { return; }
+
+        let waiting = (&initiatives, players.mask() | monsters.mask() | followers.mask()).join()
+            .any(|(i, _)| i.energy >= ACTION_THRESHOLD);
+        if waiting { return; }
+
+        clock.turns += 1;
+        clock.ticked = true;
+        for (initiative, statuses, encumbrance) in (&mut initiatives, statuses.maybe(), encumbrance.maybe()).join() {
+            initiative.energy += effective_speed(initiative.speed, statuses, encumbrance);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::StatusEffect;
+
+    fn with_status(kind : StatusKind) -> StatusEffects {
+        let mut statuses = StatusEffects::default();
+        statuses.add(StatusEffect{ kind, turns: 5, magnitude: 0 });
+        statuses
+    }
+
+    #[test]
+    fn haste_and_slow_change_speed() {
+        assert_eq!(effective_speed(NORMAL_SPEED, None, None), NORMAL_SPEED);
+        assert_eq!(effective_speed(NORMAL_SPEED, Some(&with_status(StatusKind::Haste)), None), NORMAL_SPEED * 2);
+        assert_eq!(effective_speed(NORMAL_SPEED, Some(&with_status(StatusKind::Slow)), None), NORMAL_SPEED / 2);
+    }
+
+    #[test]
+    fn carrying_too_much_costs_a_quarter_of_speed() {
+        let light = Encumbrance{ load: 10.0, capacity: 20.0 };
+        let heavy = Encumbrance{ load: 30.0, capacity: 20.0 };
+        assert_eq!(effective_speed(NORMAL_SPEED, None, Some(&light)), NORMAL_SPEED);
+        assert_eq!(effective_speed(NORMAL_SPEED, None, Some(&heavy)), NORMAL_SPEED * 3 / 4);
+        assert_eq!(effective_speed(NORMAL_SPEED, Some(&with_status(StatusKind::Haste)), Some(&heavy)), NORMAL_SPEED * 3 / 2);
+    }
+}
@@ -1,30 +1,75 @@
 use specs::prelude::*;
-use super::{WantsToPickupItem, Name, InBackpack, Position, gamelog::GameLog, WantsToUseItem,
+use super::{WantsToPickupItem, InBackpack, Position, WantsToUseItem, Name, Quantity, Weight, ObfuscatedName, IdentifiesItems, Cursed, RemovesCurse, Charges, Recharges, Durability, Rusty, RepairsItems, WantsToStoreItem, WantsToRetrieveItem, all_components, identification::ItemIdentities,
             Consumable, ProvidesHealing, CombatStats, WantsToDropItem, InflictsDamage, Map, SufferDamage,
-            AreaOfEffect, Confusion};
+            AreaOfEffect, InflictsStatus, StatusEffects, Equippable, Equipped, Attributes, Skills,
+            ProvidesFood, HungerClock, HungerState, gamesystem, hunger_system::WELL_FED_TURNS,
+            Knockback, ForcedMovement, Spell, Mana, TeachesSpell, KnownSpells, particle_system::{ParticleBuilder, ParticleRequest},
+            stealth_system::{NoiseQueue, ITEM_NOISE}, game_events::{GameEvents, GameEvent}};
+use rltk::{Point, RGB};
+
+/// How long each ring of an area effect waits before the next one further out appears.
+const BLAST_RING_DELAY_MS : f32 = 60.0;
+
+/// Whoever ultimately has hold of things kept by `owner`, looking through any containers.
+pub fn carrier(backpack : &ReadStorage<InBackpack>, owner : Entity) -> Entity {
+    match backpack.get(owner) {
+        Some(pack) => carrier(backpack, pack.owner),
+        None => owner
+    }
+}
+
+/// Puts `item` in `owner`'s keeping. A stackable item joins a matching unequipped stack
+/// already there if it can, cursed items only with cursed ones, and is then removed at the
+/// end of the turn; whichever entity now holds it is returned.
+#[allow(clippy::too_many_arguments)]
+fn stow(item : Entity, owner : Entity, entities : &Entities, lazy : &LazyUpdate, backpack : &mut WriteStorage<InBackpack>,
+        names : &ReadStorage<Name>, quantities : &mut WriteStorage<Quantity>, equipped : &ReadStorage<Equipped>,
+        cursed : &ReadStorage<Cursed>) -> Entity {
+    let stack = match (names.get(item), quantities.get(item)) {
+        (Some(name), Some(_)) => (entities, &*backpack, names, &*quantities, !equipped).join()
+            .find(|(entity, pack, other, _, _)| *entity != item && pack.owner == owner && other.name == name.name
+                && cursed.contains(*entity) == cursed.contains(item))
+            .map(|(entity, _, _, _, _)| entity),
+        _ => None
+    };
+
+    let Some(stack) = stack else {
+        backpack.insert(item, InBackpack{ owner }).expect("Unable to insert backpack entry");
+        return item;
+    };
+    let amount = quantities.get(item).map_or(1, |q| q.amount);
+    if let Some(existing) = quantities.get_mut(stack) {
+        existing.amount += amount;
+    }
+    backpack.remove(item);
+    lazy.exec_mut(move |world| { world.delete_entity(item).expect("Unable to delete"); });
+    stack
+}
 
 pub struct ItemCollectionSystem {}
 
 impl<'a> System<'a> for ItemCollectionSystem {
     #[allow(clippy::type_complexity)]
-    type SystemData = ( ReadExpect<'a, Entity>,
-                        WriteExpect<'a, GameLog>,
+    type SystemData = ( WriteExpect<'a, GameEvents>,
+                        Entities<'a>,
+                        Read<'a, LazyUpdate>,
                         WriteStorage<'a, WantsToPickupItem>,
                         WriteStorage<'a, Position>,
+                        WriteStorage<'a, InBackpack>,
                         ReadStorage<'a, Name>,
-                        WriteStorage<'a, InBackpack>
+                        WriteStorage<'a, Quantity>,
+                        ReadStorage<'a, Equipped>,
+                        ReadStorage<'a, Cursed>
     );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) = data;
+        let (mut events, entities, lazy, mut wants_pickup, mut positions, mut backpack, names, mut quantities, equipped, cursed) = data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
-            backpack.insert(pickup.item, InBackpack{ owner: pickup.collected_by }).expect("Unable to insert backpack entry");
-
-            if pickup.collected_by == *player_entity {
-                gamelog.entries.push(format!("You pick up the {}.", names.get(pickup.item).unwrap().name));
-            }
+            let item = stow(pickup.item, pickup.collected_by, &entities, &lazy, &mut backpack, &names, &mut quantities,
+                &equipped, &cursed);
+            events.publish(GameEvent::ItemPickedUp{ collector: pickup.collected_by, item });
         }
 
         wants_pickup.clear();
@@ -35,33 +80,127 @@ pub struct ItemUseSystem {}
 
 impl<'a> System<'a> for ItemUseSystem {
     #[allow(clippy::type_complexity)]
-    type SystemData = ( ReadExpect<'a, Entity>,
-                        WriteExpect<'a, GameLog>,
+    type SystemData = ( WriteExpect<'a, GameEvents>,
                         ReadExpect<'a, Map>,
                         Entities<'a>,
+                        Read<'a, LazyUpdate>,
                         WriteStorage<'a, WantsToUseItem>,
-                        ReadStorage<'a, Name>,
-                        ReadStorage<'a, Consumable>,
+                        ( ReadStorage<'a, Consumable>,
+                          WriteStorage<'a, Quantity>,
+                          WriteStorage<'a, Charges>,
+                          ReadStorage<'a, Recharges>,
+                          WriteStorage<'a, Durability>,
+                          WriteStorage<'a, Rusty>,
+                          ReadStorage<'a, RepairsItems> ),
                         ReadStorage<'a, ProvidesHealing>,
                         ReadStorage<'a, InflictsDamage>,
                         WriteStorage<'a, CombatStats>,
                         WriteStorage<'a, SufferDamage>,
                         ReadStorage<'a, AreaOfEffect>,
-                        WriteStorage<'a, Confusion>
+                        ReadStorage<'a, InflictsStatus>,
+                        WriteStorage<'a, StatusEffects>,
+                        ReadStorage<'a, Equippable>,
+                        WriteStorage<'a, Equipped>,
+                        ReadStorage<'a, Attributes>,
+                        ReadStorage<'a, Skills>,
+                        ReadStorage<'a, ProvidesFood>,
+                        WriteStorage<'a, HungerClock>,
+                        ReadStorage<'a, Position>,
+                        WriteExpect<'a, ParticleBuilder>,
+                        ReadStorage<'a, Knockback>,
+                        WriteStorage<'a, ForcedMovement>,
+                        WriteExpect<'a, NoiseQueue>,
+                        ( ReadStorage<'a, Spell>,
+                          WriteStorage<'a, Mana>,
+                          ReadStorage<'a, TeachesSpell>,
+                          WriteStorage<'a, KnownSpells> ),
+                        ( ReadExpect<'a, ItemIdentities>,
+                          ReadStorage<'a, Name>,
+                          ReadStorage<'a, ObfuscatedName>,
+                          ReadStorage<'a, IdentifiesItems>,
+                          ReadStorage<'a, InBackpack>,
+                          WriteStorage<'a, Cursed>,
+                          ReadStorage<'a, RemovesCurse> )
     );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (player_entity, mut gamelog, map, entities, mut wants_use, names,
-            consumables, healing, inflict_damage, mut combat_stats, mut suffer_damage,
-            aoe, mut confused) = data;
+        let (mut events, map, entities, lazy, mut wants_use,
+            (consumables, mut quantities, mut charges, recharges, mut durability, mut rusty, repairs), healing, inflict_damage, mut combat_stats, mut suffer_damage,
+            aoe, inflicts_status, mut statuses, equippable, mut equipped, attributes, skills,
+            provides_food, mut hunger_clocks, positions, mut particles, knockbacks, mut forced_movement, mut noise,
+            (spells, mut mana, teaches_spell, mut known_spells),
+            (identities, names, obfuscated, identifies, backpack, mut cursed, removes_curse)) = data;
 
         for (entity, useitem) in (&entities, &wants_use).join() {
+            // Spells are paid for with mana rather than by using up an item
+            if let Some(spell) = spells.get(useitem.item) {
+                match mana.get_mut(entity) {
+                    Some(pool) if pool.current >= spell.mana_cost => pool.current -= spell.mana_cost,
+                    _ => continue
+                }
+                events.publish(GameEvent::SpellCast{ caster: entity, spell: useitem.item });
+            }
+
+            // Each use of a wand takes a charge; an empty one does nothing, but stays around to be recharged
+            if let Some(charges) = charges.get_mut(useitem.item) {
+                if charges.current < 1 {
+                    events.publish(GameEvent::NothingHappens{ user: entity, item: useitem.item });
+                    continue;
+                }
+                charges.current -= 1;
+            }
+
             let mut used_item = true;
+            let spell_power = gamesystem::spell_power(&attributes.get(entity).cloned().unwrap_or_default(),
+                &skills.get(entity).cloned().unwrap_or_default());
+
+            // Using worn gear takes it off; using other gear swaps it with whatever fills the slot
+            if let Some(can_equip) = equippable.get(useitem.item) {
+                let target_slot = can_equip.slot;
+                let already_equipped = equipped.get(useitem.item).is_some();
+
+                let mut to_unequip : Vec<Entity> = Vec::new();
+                for (item_entity, already) in (&entities, &equipped).join() {
+                    if already.owner == entity && already.slot == target_slot {
+                        to_unequip.push(item_entity);
+                    }
+                }
+                // Cursed gear stays where it is
+                if let Some(stuck) = to_unequip.iter().find(|item| cursed.get(**item).is_some()) {
+                    events.publish(GameEvent::CurseStuck{ owner: entity, item: *stuck });
+                    continue;
+                }
+                for item in to_unequip.iter() {
+                    equipped.remove(*item);
+                    events.publish(GameEvent::ItemUnequipped{ owner: entity, item: *item });
+                }
+
+                if !already_equipped {
+                    equipped.insert(useitem.item, Equipped{ owner: entity, slot: target_slot }).expect("Unable to insert equipped component");
+                    events.publish(GameEvent::ItemEquipped{ owner: entity, item: useitem.item });
+                }
+                continue;
+            }
+
+            if let Some(pos) = positions.get(entity) {
+                noise.make(pos.x, pos.y, ITEM_NOISE);
+            }
+
+            // Cursed scrolls ignore where they were aimed and go off on the reader
+            let backfires = cursed.get(useitem.item).is_some();
+            if backfires {
+                events.publish(GameEvent::Backfire{ user: entity, item: useitem.item });
+            }
+            let aimed_at = if backfires { None } else { useitem.target };
 
             // Targeting
             let mut targets : Vec<Entity> = Vec::new();
-            match useitem.target {
-                None => { targets.push( *player_entity ); }
+            // Area effects used without a target are centred on the user, who is spared
+            let centre = aimed_at.or_else(|| {
+                aoe.get(useitem.item).and(positions.get(entity)).map(|pos| Point::new(pos.x, pos.y))
+            });
+            match centre {
+                None => { targets.push( entity ); }
                 Some(target) => {
                     let area_effect = aoe.get(useitem.item);
                     match area_effect {
@@ -77,6 +216,10 @@ impl<'a> System<'a> for ItemUseSystem {
                             let mut blast_tiles = rltk::field_of_view(target, area_effect.radius, &*map);
                             blast_tiles.retain(|p| p.x > 0 && p.x < map.width-1 && p.y > 0 && p.y < map.height-1 );
                             for tile_index in blast_tiles.iter() {
+                                let distance = rltk::DistanceAlg::Pythagoras.distance2d(target, *tile_index);
+                                particles.push(ParticleRequest{ x: tile_index.x, y: tile_index.y,
+                                    foreground: RGB::named(rltk::ORANGE), background: RGB::named(rltk::BLACK),
+                                    glyph: rltk::to_cp437('░'), lifetime_ms: 200.0, delay_ms: distance.round() * BLAST_RING_DELAY_MS });
                                 let index = map.xy_index(tile_index.x, tile_index.y);
                                 for mob in map.tile_content[index].iter() {
                                     targets.push(*mob);
@@ -87,6 +230,80 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
+            if aimed_at.is_none() && centre.is_some() && !backfires {
+                targets.retain(|target| *target != entity);
+            }
+
+            // Spellbooks teach their spell to whoever reads them
+            if let Some(teaches) = teaches_spell.get(useitem.item) {
+                used_item = false;
+                if let Some(known) = known_spells.get_mut(entity) {
+                    if !known.knows(&names, teaches.spell) {
+                        known.spells.push(teaches.spell);
+                        events.publish(GameEvent::SpellLearned{ entity, spell: teaches.spell });
+                        used_item = true;
+                    }
+                }
+            }
+
+            // Remove curse lifts every curse on what the reader carries
+            if removes_curse.get(useitem.item).is_some() {
+                let lifted : Vec<Entity> = (&entities, &backpack, &cursed).join()
+                    .filter(|(_, pack, _)| carrier(&backpack, pack.owner) == entity)
+                    .map(|(item, _, _)| item)
+                    .collect();
+                for item in lifted.iter() {
+                    cursed.remove(*item);
+                    events.publish(GameEvent::CurseRemoved{ owner: entity, item: *item });
+                }
+                if lifted.is_empty() {
+                    events.publish(GameEvent::ItemUsed{ user: entity, item: useitem.item });
+                }
+            }
+
+            // Recharging refills everything the reader carries that runs on charges
+            if recharges.get(useitem.item).is_some() {
+                let refilled : Vec<Entity> = (&entities, &backpack, &mut charges).join()
+                    .filter(|(_, pack, _)| carrier(&backpack, pack.owner) == entity)
+                    .map(|(item, _, charges)| { charges.current = charges.max; item })
+                    .collect();
+                for item in refilled.iter() {
+                    events.publish(GameEvent::Recharged{ owner: entity, item: *item });
+                }
+                if refilled.is_empty() {
+                    events.publish(GameEvent::ItemUsed{ user: entity, item: useitem.item });
+                }
+            }
+
+            // Repair kits mend everything the user carries, rust and all
+            if repairs.get(useitem.item).is_some() {
+                let repaired : Vec<Entity> = (&entities, &backpack, &mut durability).join()
+                    .filter(|(_, pack, durability)| carrier(&backpack, pack.owner) == entity && durability.current < durability.max)
+                    .map(|(item, _, durability)| { durability.current = durability.max; item })
+                    .collect();
+                for item in repaired.iter() {
+                    rusty.remove(*item);
+                    events.publish(GameEvent::Repaired{ owner: entity, item: *item });
+                }
+                if repaired.is_empty() {
+                    used_item = false;
+                    events.publish(GameEvent::NothingHappens{ user: entity, item: useitem.item });
+                }
+            }
+
+            // Food resets the eater's hunger
+            if provides_food.get(useitem.item).is_some() {
+                used_item = false;
+                for target in targets.iter() {
+                    if let Some(hunger) = hunger_clocks.get_mut(*target) {
+                        hunger.state = HungerState::WellFed;
+                        hunger.duration = WELL_FED_TURNS;
+                        events.publish(GameEvent::Ate{ eater: *target, item: useitem.item });
+                        used_item = true;
+                    }
+                }
+            }
+
             // If it heals, apply the healing
             let item_heals = healing.get(useitem.item);
             match item_heals {
@@ -97,9 +314,15 @@ impl<'a> System<'a> for ItemUseSystem {
                         let stats = combat_stats.get_mut(*target);
                         if let Some(stats) = stats {
                             stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
-                            if entity == *player_entity {
-                                gamelog.entries.push(format!("You use the {}, healing {} hp.", names.get(useitem.item).unwrap().name, healer.heal_amount));
+                            if let Some(pos) = positions.get(*target) {
+                                particles.request(pos.x, pos.y, RGB::named(rltk::GREEN), rltk::to_cp437('♥'), 200.0);
+                                for (delay, (dx, dy)) in [(-1, -1), (1, -1), (-1, 1), (1, 1)].iter().enumerate() {
+                                    particles.push(ParticleRequest{ x: pos.x + dx, y: pos.y + dy,
+                                        foreground: RGB::named(rltk::LIGHT_GREEN), background: RGB::named(rltk::BLACK),
+                                        glyph: rltk::to_cp437('*'), lifetime_ms: 150.0, delay_ms: 50.0 * delay as f32 });
+                                }
                             }
+                            events.publish(GameEvent::Heal{ target: *target, item: useitem.item, amount: healer.heal_amount });
                             used_item = true;
                         }
                     }
@@ -112,49 +335,67 @@ impl<'a> System<'a> for ItemUseSystem {
                 None => {}
                 Some(damage) => {
                     used_item = false;
+                    let amount = i32::max(1, damage.damage + spell_power * 2);
                     for mob in targets.iter() {
-                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage.damage);
-                        if entity == *player_entity {
-                            let mob_name = names.get(*mob).unwrap();
-                            let item_name = names.get(useitem.item).unwrap();
-                            gamelog.entries.push(format!("You use {} on {}, inflicting {} hp.", item_name.name, mob_name.name, damage.damage));
-                        }
+                        SufferDamage::new_damage(&mut suffer_damage, *mob, amount, damage.damage_type, Some(entity));
+                        events.publish(GameEvent::Damage{ source: entity, target: *mob, item: useitem.item, amount, damage_type: damage.damage_type });
 
                         used_item = true;
                     }
                 }
             }
 
-            // Can it pass along confusion? Note the use of scopes to escape from the borrow checker!
-            let mut add_confusion = Vec::new();
-            {
-                let causes_confusion = confused.get(useitem.item);
-                match causes_confusion {
-                    None => {}
-                    Some(confusion) => {
-                        used_item = false;
-                        for mob in targets.iter() {
-                            add_confusion.push((*mob, confusion.turns ));
-                            if entity == *player_entity {
-                                let mob_name = names.get(*mob).unwrap();
-                                let item_name = names.get(useitem.item).unwrap();
-                                gamelog.entries.push(format!("You use {} on {}, confusing them.", item_name.name, mob_name.name));
-                            }
-                        }
-                    }
+            // Can it pass along a status effect?
+            if let Some(inflicts) = inflicts_status.get(useitem.item) {
+                used_item = false;
+                let mut effect = inflicts.effect();
+                effect.turns = i32::max(1, effect.turns + spell_power);
+                for mob in targets.iter().filter(|mob| combat_stats.get(**mob).is_some()) {
+                    StatusEffects::add_effect(&mut statuses, *mob, effect);
+                    events.publish(GameEvent::StatusInflicted{ source: entity, target: *mob, item: Some(useitem.item), kind: inflicts.kind });
+                    used_item = true;
+                }
+            }
+
+            // Push everything caught by it away from the centre
+            if let (Some(knockback), Some(centre)) = (knockbacks.get(useitem.item), centre) {
+                used_item = false;
+                for mob in targets.iter().filter(|mob| combat_stats.get(**mob).is_some()) {
+                    forced_movement.insert(*mob, ForcedMovement{ origin: centre, distance: knockback.distance, source: Some(entity) })
+                        .expect("Unable to insert forced movement");
+                    used_item = true;
+                }
+                if used_item {
+                    events.publish(GameEvent::ItemUsed{ user: entity, item: useitem.item });
                 }
             }
-            for mob in add_confusion.iter() {
-                confused.insert(mob.0, Confusion{ turns: mob.1 }).expect("Unable to insert status");
+
+            // Using a magic item shows what it is, and identify scrolls show what everything carried is
+            let mut to_identify : Vec<Entity> = Vec::new();
+            if used_item { to_identify.push(useitem.item); }
+            if identifies.get(useitem.item).is_some() {
+                for (carried, _pack) in (&entities, &backpack).join().filter(|(_, pack)| carrier(&backpack, pack.owner) == entity) {
+                    to_identify.push(carried);
+                }
+            }
+            let mut identified_names : Vec<&str> = Vec::new();
+            for item in to_identify {
+                if let (Some(name), Some(_)) = (names.get(item), obfuscated.get(item)) {
+                    if !identities.is_identified(&name.name) && !identified_names.contains(&name.name.as_str()) {
+                        identified_names.push(&name.name);
+                        events.publish(GameEvent::ItemIdentified{ entity, item });
+                    }
+                }
             }
 
-            // If its a consumable, we delete it on use
-            if used_item {
-                let consumable = consumables.get(useitem.item);
-                match consumable {
-                    None => {}
-                    Some(_) => {
-                        entities.delete(useitem.item).expect("Delete failed");
+            // If its a consumable, we use up one of the stack, deleting the last one. That waits
+            // for the end of the turn, so the item is still around when its events are reported.
+            if used_item && consumables.get(useitem.item).is_some() {
+                match quantities.get_mut(useitem.item) {
+                    Some(stack) if stack.amount > 1 => stack.amount -= 1,
+                    _ => {
+                        let item = useitem.item;
+                        lazy.exec_mut(move |world| { world.delete_entity(item).expect("Delete failed"); });
                     }
                 }
             }
@@ -168,19 +409,23 @@ pub struct ItemDropSystem {}
 
 impl<'a> System<'a> for ItemDropSystem {
     #[allow(clippy::type_complexity)]
-    type SystemData = ( ReadExpect<'a, Entity>,
-                        WriteExpect<'a, GameLog>,
+    type SystemData = ( WriteExpect<'a, GameEvents>,
                         Entities<'a>,
                         WriteStorage<'a, WantsToDropItem>,
-                        ReadStorage<'a, Name>,
                         WriteStorage<'a, Position>,
-                        WriteStorage<'a, InBackpack>
+                        WriteStorage<'a, InBackpack>,
+                        WriteStorage<'a, Equipped>,
+                        ReadStorage<'a, Cursed>
     );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (player_entity, mut gamelog, entities, mut wants_drop, names, mut positions, mut backpack) = data;
+        let (mut events, entities, mut wants_drop, mut positions, mut backpack, mut equipped, cursed) = data;
 
         for (entity, to_drop) in (&entities, &wants_drop).join() {
+            if equipped.contains(to_drop.item) && cursed.contains(to_drop.item) {
+                events.publish(GameEvent::CurseStuck{ owner: entity, item: to_drop.item });
+                continue;
+            }
             let mut dropper_pos : Position = Position{x:0, y:0};
             {
                 let dropped_pos = positions.get(entity).unwrap();
@@ -189,12 +434,276 @@ impl<'a> System<'a> for ItemDropSystem {
             }
             positions.insert(to_drop.item, Position{ x : dropper_pos.x, y : dropper_pos.y }).expect("Unable to insert position");
             backpack.remove(to_drop.item);
-
-            if entity == *player_entity {
-                gamelog.entries.push(format!("You drop the {}.", names.get(to_drop.item).unwrap().name));
-            }
+            equipped.remove(to_drop.item);
+            events.publish(GameEvent::ItemDropped{ dropper: entity, item: to_drop.item });
         }
 
         wants_drop.clear();
     }
 }
+
+/// Moves items between their owner's pack and the containers in it.
+pub struct ContainerSystem {}
+
+impl<'a> System<'a> for ContainerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( WriteExpect<'a, GameEvents>,
+                        Entities<'a>,
+                        Read<'a, LazyUpdate>,
+                        WriteStorage<'a, WantsToStoreItem>,
+                        WriteStorage<'a, WantsToRetrieveItem>,
+                        WriteStorage<'a, InBackpack>,
+                        ReadStorage<'a, Name>,
+                        WriteStorage<'a, Quantity>,
+                        ReadStorage<'a, Equipped>,
+                        ReadStorage<'a, Cursed>
+    );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut events, entities, lazy, mut wants_store, mut wants_retrieve, mut backpack, names, mut quantities, equipped,
+            cursed) = data;
+
+        for (entity, store) in (&entities, &wants_store).join() {
+            stow(store.item, store.container, &entities, &lazy, &mut backpack, &names, &mut quantities, &equipped, &cursed);
+            events.publish(GameEvent::ItemStored{ owner: entity, item: store.item, container: store.container });
+        }
+        for (entity, retrieve) in (&entities, &wants_retrieve).join() {
+            let Some(container) = backpack.get(retrieve.item).map(|pack| pack.owner) else { continue };
+            stow(retrieve.item, entity, &entities, &lazy, &mut backpack, &names, &mut quantities, &equipped, &cursed);
+            events.publish(GameEvent::ItemRetrieved{ owner: entity, item: retrieve.item, container });
+        }
+
+        wants_store.clear();
+        wants_retrieve.clear();
+    }
+}
+
+/// How much `item` weighs, counting the whole stack and anything inside it.
+pub fn total_weight(ecs : &World, item : Entity) -> f32 {
+    let weights = ecs.read_storage::<Weight>();
+    let quantities = ecs.read_storage::<Quantity>();
+    weights.get(item).map_or(0.0, |w| w.lbs) * quantities.get(item).map_or(1, |q| q.amount) as f32 + contents_weight(ecs, item)
+}
+
+/// How much is in a container.
+pub fn contents_weight(ecs : &World, container : Entity) -> f32 {
+    let weights = ecs.read_storage::<Weight>();
+    let quantities = ecs.read_storage::<Quantity>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    (&backpack, &weights, quantities.maybe()).join()
+        .filter(|(pack, _, _)| pack.owner == container)
+        .map(|(_, weight, quantity)| weight.lbs * quantity.map_or(1, |q| q.amount) as f32)
+        .sum()
+}
+
+/// Takes `amount` items off a stack into a new entity of their own, which is returned.
+/// The new stack is everything the old one is, and carried by the same owner, but not equipped.
+pub fn split_stack(ecs : &mut World, item : Entity, amount : i32) -> Entity {
+    let split = ecs.create_entity().build();
+    macro_rules! copy {
+        ($($component:ty),*) => {
+            $(
+                let copy = ecs.read_storage::<$component>().get(item).cloned();
+                if let Some(copy) = copy {
+                    ecs.write_storage::<$component>().insert(split, copy).expect("Unable to copy component");
+                }
+            )*
+        };
+    }
+    all_components!(copy);
+    ecs.write_storage::<Equipped>().remove(split);
+
+    let mut quantities = ecs.write_storage::<Quantity>();
+    if let Some(stack) = quantities.get_mut(item) {
+        stack.amount -= amount;
+    }
+    quantities.insert(split, Quantity{ amount }).expect("Unable to insert quantity");
+    split
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Item, Scroll, Container, Throwable, EquipmentSlot};
+
+    /// A world with everything items can be made of, and a player to use them.
+    fn world_with_player() -> (World, Entity) {
+        let mut ecs = World::new();
+        macro_rules! register { ($($component:ty),*) => { $( ecs.register::<$component>(); )* }; }
+        all_components!(register);
+        ecs.insert(GameEvents::default());
+        ecs.insert(Map::default());
+        ecs.insert(ParticleBuilder::new());
+        ecs.insert(NoiseQueue::new());
+        ecs.insert(ItemIdentities::default());
+
+        let player = ecs.create_entity().with(Name{ name: "Player".to_string() }).build();
+        ecs.insert(player);
+        (ecs, player)
+    }
+
+    /// The player, carrying five health potions.
+    fn player_with_potions() -> (World, Entity, Entity) {
+        let (mut ecs, player) = world_with_player();
+        let potions = ecs.create_entity()
+            .with(Name{ name: "Health Potion".to_string() })
+            .with(Item{})
+            .with(Consumable{})
+            .with(ProvidesHealing{ heal_amount: 8 })
+            .with(Weight{ lbs: 0.5 })
+            .with(InBackpack{ owner: player })
+            .with(Quantity{ amount: 5 })
+            .build();
+        (ecs, player, potions)
+    }
+
+    fn use_item(ecs : &mut World, user : Entity, item : Entity) {
+        ecs.write_storage::<WantsToUseItem>().insert(user, WantsToUseItem{ item, target: None }).unwrap();
+        ItemUseSystem{}.run_now(ecs);
+        ecs.maintain();
+    }
+
+    fn carried<'a>(ecs : &'a mut World, owner : Entity, name : &str) -> EntityBuilder<'a> {
+        ecs.create_entity()
+            .with(Name{ name: name.to_string() })
+            .with(Item{})
+            .with(InBackpack{ owner })
+    }
+
+    #[test]
+    fn split_stack_takes_items_off_the_top() {
+        let (mut ecs, player, potions) = player_with_potions();
+        let split = split_stack(&mut ecs, potions, 2);
+
+        let quantities = ecs.read_storage::<Quantity>();
+        assert_eq!(quantities.get(potions).unwrap().amount, 3);
+        assert_eq!(quantities.get(split).unwrap().amount, 2);
+        assert_eq!(ecs.read_storage::<InBackpack>().get(split).unwrap().owner, player);
+    }
+
+    #[test]
+    fn split_stack_copies_what_the_items_do() {
+        let (mut ecs, _, potions) = player_with_potions();
+        let split = split_stack(&mut ecs, potions, 1);
+
+        assert_eq!(ecs.read_storage::<Name>().get(split).unwrap().name, "Health Potion");
+        assert_eq!(ecs.read_storage::<ProvidesHealing>().get(split).unwrap().heal_amount, 8);
+        assert!(ecs.read_storage::<Consumable>().get(split).is_some());
+        assert!(ecs.read_storage::<Cursed>().get(split).is_none());
+    }
+
+    #[test]
+    fn split_stack_leaves_nothing_behind() {
+        let (mut ecs, _, potions) = player_with_potions();
+        ecs.write_storage::<Throwable>().insert(potions, Throwable{ range: 5 }).unwrap();
+        let split = split_stack(&mut ecs, potions, 1);
+
+        assert_eq!(ecs.read_storage::<Throwable>().get(split).unwrap().range, 5);
+        macro_rules! same_components {
+            ($($component:ty),*) => {
+                $(
+                    let storage = ecs.read_storage::<$component>();
+                    assert_eq!(storage.contains(split), storage.contains(potions), "{} wasn't copied", stringify!($component));
+                )*
+            };
+        }
+        all_components!(same_components);
+    }
+
+    #[test]
+    fn split_stack_is_not_equipped() {
+        let (mut ecs, player, potions) = player_with_potions();
+        ecs.write_storage::<Equipped>().insert(potions, Equipped{ owner: player, slot: EquipmentSlot::Melee }).unwrap();
+        let split = split_stack(&mut ecs, potions, 1);
+
+        assert!(ecs.read_storage::<Equipped>().get(split).is_none());
+    }
+
+    #[test]
+    fn cursed_items_stack_apart_from_clean_ones() {
+        let (mut ecs, player, potions) = player_with_potions();
+        let cursed = split_stack(&mut ecs, potions, 1);
+        ecs.write_storage::<InBackpack>().remove(cursed);
+        ecs.write_storage::<Position>().insert(cursed, Position{ x: 1, y: 1 }).unwrap();
+        ecs.write_storage::<Cursed>().insert(cursed, Cursed{}).unwrap();
+
+        ecs.write_storage::<WantsToPickupItem>().insert(player, WantsToPickupItem{ collected_by: player, item: cursed }).unwrap();
+        ItemCollectionSystem{}.run_now(&ecs);
+        ecs.maintain();
+
+        assert!(ecs.entities().is_alive(cursed));
+        assert_eq!(ecs.read_storage::<InBackpack>().get(cursed).unwrap().owner, player);
+        assert_eq!(ecs.read_storage::<Quantity>().get(potions).unwrap().amount, 4);
+        assert!(ecs.read_storage::<Cursed>().get(potions).is_none());
+    }
+
+    #[test]
+    fn wands_use_a_charge_and_do_nothing_when_empty() {
+        let (mut ecs, player) = world_with_player();
+        let wand = carried(&mut ecs, player, "Wand of Sparks").with(Charges{ current: 1, max: 3 }).build();
+
+        use_item(&mut ecs, player, wand);
+        assert_eq!(ecs.read_storage::<Charges>().get(wand).unwrap().current, 0);
+        ecs.write_resource::<GameEvents>().events.clear();
+
+        use_item(&mut ecs, player, wand);
+        assert!(ecs.entities().is_alive(wand));
+        assert_eq!(ecs.fetch::<GameEvents>().events, vec![GameEvent::NothingHappens{ user: player, item: wand }]);
+    }
+
+    #[test]
+    fn recharging_refills_carried_wands() {
+        let (mut ecs, player) = world_with_player();
+        let wand = carried(&mut ecs, player, "Wand of Sparks").with(Charges{ current: 0, max: 3 }).build();
+        let scroll = carried(&mut ecs, player, "Scroll of Recharging").with(Consumable{}).with(Recharges{}).build();
+
+        use_item(&mut ecs, player, scroll);
+        assert_eq!(ecs.read_storage::<Charges>().get(wand).unwrap().current, 3);
+        assert!(!ecs.entities().is_alive(scroll));
+    }
+
+    #[test]
+    fn split_scrolls_stay_scrolls() {
+        let (mut ecs, player) = world_with_player();
+        let scrolls = carried(&mut ecs, player, "Scroll of Identify").with(Scroll{}).with(Quantity{ amount: 2 }).build();
+        let split = split_stack(&mut ecs, scrolls, 1);
+
+        assert!(ecs.read_storage::<Scroll>().get(split).is_some());
+    }
+
+    #[test]
+    fn items_taken_from_a_bag_join_the_stack_in_the_pack() {
+        let (mut ecs, player, potions) = player_with_potions();
+        let bag = carried(&mut ecs, player, "Bag").with(Container{ max_weight: 20.0, scrolls_only: false }).build();
+        let bagged = split_stack(&mut ecs, potions, 2);
+        ecs.write_storage::<InBackpack>().insert(bagged, InBackpack{ owner: bag }).unwrap();
+
+        ecs.write_storage::<WantsToRetrieveItem>().insert(player, WantsToRetrieveItem{ item: bagged }).unwrap();
+        ContainerSystem{}.run_now(&ecs);
+        ecs.maintain();
+
+        assert!(!ecs.entities().is_alive(bagged));
+        assert_eq!(ecs.read_storage::<Quantity>().get(potions).unwrap().amount, 5);
+    }
+
+    #[test]
+    fn scrolls_reach_into_carried_bags() {
+        let (mut ecs, player) = world_with_player();
+        let bag = carried(&mut ecs, player, "Bag").with(Container{ max_weight: 20.0, scrolls_only: false }).build();
+        let wand = carried(&mut ecs, bag, "Wand of Sparks").with(Charges{ current: 0, max: 3 }).build();
+        let scroll = carried(&mut ecs, player, "Scroll of Recharging").with(Consumable{}).with(Recharges{}).build();
+
+        use_item(&mut ecs, player, scroll);
+        assert_eq!(ecs.read_storage::<Charges>().get(wand).unwrap().current, 3);
+    }
+
+    #[test]
+    fn bags_weigh_what_they_hold() {
+        let (mut ecs, player, potions) = player_with_potions();
+        let bag = carried(&mut ecs, player, "Bag").with(Weight{ lbs: 1.0 }).build();
+        ecs.write_storage::<InBackpack>().insert(potions, InBackpack{ owner: bag }).unwrap();
+
+        assert_eq!(contents_weight(&ecs, bag), 2.5);
+        assert_eq!(total_weight(&ecs, bag), 3.5);
+    }
+}
@@ -17,19 +17,48 @@ mod map_indexing_system;
 use map_indexing_system::MapIndexingSystem;
 mod melee_combat_system;
 use melee_combat_system::MeleeCombatSystem;
+mod ranged_combat_system;
+use ranged_combat_system::RangedCombatSystem;
 mod damage_system;
 use damage_system::DamageSystem;
+mod status_effect_system;
+use status_effect_system::StatusEffectSystem;
+mod initiative_system;
+use initiative_system::InitiativeSystem;
+mod hunger_system;
+use hunger_system::HungerSystem;
+mod particle_system;
+mod stealth_system;
+mod morale_system;
+mod regeneration_system;
+mod ally_ai_system;
+use ally_ai_system::AllyAI;
+use regeneration_system::RegenerationSystem;
+use morale_system::MoraleSystem;
+use stealth_system::PerceptionSystem;
+mod forced_movement_system;
+use forced_movement_system::ForcedMovementSystem;
+mod encumbrance_system;
+mod identification;
+mod durability_system;
+use durability_system::DurabilitySystem;
+use encumbrance_system::EncumbranceSystem;
 mod gui;
 mod gamelog;
+mod game_events;
+mod gamesystem;
+mod morgue;
 mod spawner;
 mod inventory_system;
-use inventory_system::{ ItemCollectionSystem, ItemUseSystem, ItemDropSystem };
+use inventory_system::{ ItemCollectionSystem, ItemUseSystem, ItemDropSystem, ContainerSystem };
 
 
 
 #[derive(PartialEq, Copy, Clone)]
-pub enum RunState { AwaitingInput, PreRun, PlayerTurn, MonsterTurn, ShowInventory, ShowDropItem,
-    ShowTargeting { range : i32, item : Entity} }
+pub enum RunState { AwaitingInput, PreRun, PlayerTurn, Ticking, ShowInventory, ShowDropItem,
+    ShowTargeting { range : i32, item : Entity}, ShowThrowItem, ShowFireTargeting { range : i32, item : Entity },
+    ShowCastSpell, ShowDropQuantity { item : Entity, amount : i32 }, ShowBag { bag : Entity }, ShowPutAway,
+    ShowChooseContainer { item : Entity }, GameOver, LevelUp }
 
 
 pub struct State {
@@ -40,28 +69,83 @@ impl State {
     fn run_systems(&mut self) {
         let mut vis = VisibilitySystem{};
         vis.run_now(&self.ecs);
+        let mut initiative = InitiativeSystem{};
+        initiative.run_now(&self.ecs);
+        let mut statuses = StatusEffectSystem{};
+        statuses.run_now(&self.ecs);
+        let mut hunger = HungerSystem{};
+        hunger.run_now(&self.ecs);
+        let mut regeneration = RegenerationSystem{};
+        regeneration.run_now(&self.ecs);
+        let mut perception = PerceptionSystem{};
+        perception.run_now(&self.ecs);
+        let mut morale = MoraleSystem{};
+        morale.run_now(&self.ecs);
         let mut mob = MonsterAI{};
         mob.run_now(&self.ecs);
+        let mut allies = AllyAI{};
+        allies.run_now(&self.ecs);
         let mut mapindex = MapIndexingSystem{};
         mapindex.run_now(&self.ecs);
         let mut melee = MeleeCombatSystem{};
         melee.run_now(&self.ecs);
-        let mut damage = DamageSystem{};
-        damage.run_now(&self.ecs);
+        let mut ranged = RangedCombatSystem{};
+        ranged.run_now(&self.ecs);
         let mut pickup = ItemCollectionSystem{};
         pickup.run_now(&self.ecs);
         let mut itemuse = ItemUseSystem{};
         itemuse.run_now(&self.ecs);
         let mut drop_items = ItemDropSystem{};
         drop_items.run_now(&self.ecs);
+        let mut containers = ContainerSystem{};
+        containers.run_now(&self.ecs);
+        let mut encumbrance = EncumbranceSystem{};
+        encumbrance.run_now(&self.ecs);
+        let mut forced_movement = ForcedMovementSystem{};
+        forced_movement.run_now(&self.ecs);
+        let mut damage = DamageSystem{};
+        damage.run_now(&self.ecs);
+        let mut durability = DurabilitySystem{};
+        durability.run_now(&self.ecs);
 
+        game_events::dispatch(&self.ecs);
         self.ecs.maintain();
     }
+
+    /// Builds a fresh map and player, wiping whatever was left of a previous run.
+    fn new_run(&mut self) {
+        self.ecs.delete_all();
+        // Magic items get their disguises as they spawn, so this has to be fresh first
+        self.ecs.insert(identification::ItemIdentities::default());
+
+        let map : Map = Map::random_room_dungeon();
+        let (player_x, player_y) = map.rooms[0].center();
+
+        let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
+        spawner::dog(&mut self.ecs, player_x + 1, player_y, player_entity);
+
+        for room in map.rooms.iter().skip(1) {
+            spawner::spawn_room(&mut self.ecs, room);
+        }
+
+        self.ecs.insert(map);
+        self.ecs.insert(Point::new(player_x, player_y));
+        self.ecs.insert(player_entity);
+        self.ecs.insert(RunState::PreRun);
+        self.ecs.insert(initiative_system::GameClock::default());
+        self.ecs.insert(morgue::RunStats::default());
+        self.ecs.insert(particle_system::ParticleBuilder::new());
+        self.ecs.insert(stealth_system::NoiseQueue::new());
+        self.ecs.insert(durability_system::WearQueue::new());
+        self.ecs.insert(game_events::GameEvents::default());
+        self.ecs.insert(gamelog::GameLog{ entries : vec!["Welcome to Rusty Roguelike".to_string()] });
+    }
 }
 
 impl GameState for State {
     fn tick(&mut self, context : &mut Rltk) {
         context.cls();
+        particle_system::update_particles(&mut self.ecs, context);
 
         draw_map(&self.ecs, context);
 
@@ -71,7 +155,7 @@ impl GameState for State {
             let map = self.ecs.fetch::<Map>();
 
             let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-            data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order) );
+            data.sort_by_key(|&(_, r)| std::cmp::Reverse(r.render_order));
             for (pos, render) in data.iter() {
                 let index = map.xy_index(pos.x, pos.y);
                 if map.visible_tiles[index] { context.set(pos.x, pos.y, render.foreground, render.background, render.glyph) }
@@ -93,17 +177,55 @@ impl GameState for State {
                 newrunstate = RunState::AwaitingInput;
             }
             RunState::AwaitingInput => {
-                newrunstate = player_input(self, context);
+                if status_effect_system::player_can_act(&self.ecs) {
+                    newrunstate = player_input(self, context);
+                } else {
+                    initiative_system::spend_player_energy(&self.ecs, ActionType::Wait);
+                    newrunstate = RunState::PlayerTurn;
+                }
             }
             RunState::PlayerTurn => {
                 self.run_systems();
                 self.ecs.maintain();
-                newrunstate = RunState::MonsterTurn;
+                if initiative_system::player_ready(&self.ecs) {
+                    newrunstate = RunState::AwaitingInput;
+                } else {
+                    newrunstate = RunState::Ticking;
+                }
             }
-            RunState::MonsterTurn => {
-                self.run_systems();
-                self.ecs.maintain();
-                newrunstate = RunState::AwaitingInput;
+            RunState::Ticking => {
+                // Let time pass until everyone else has acted and the player can act again
+                loop {
+                    self.run_systems();
+                    self.ecs.maintain();
+                    damage_system::delete_the_dead(&mut self.ecs);
+                    // Dying or levelling up interrupts the passage of time
+                    if *self.ecs.fetch::<RunState>() != RunState::Ticking { break; }
+                    if initiative_system::player_ready(&self.ecs) && !initiative_system::others_ready(&self.ecs) { break; }
+                }
+                newrunstate = *self.ecs.fetch::<RunState>();
+                if newrunstate == RunState::Ticking {
+                    newrunstate = RunState::AwaitingInput;
+                }
+            }
+            RunState::LevelUp => {
+                let result = gui::level_up_menu(&self.ecs, context);
+                if let Some(choice) = result {
+                    player::level_up(&mut self.ecs, choice);
+                    if !player::can_level_up(&self.ecs) {
+                        if initiative_system::player_ready(&self.ecs) {
+                            newrunstate = RunState::AwaitingInput;
+                        } else {
+                            newrunstate = RunState::Ticking;
+                        }
+                    }
+                }
+            }
+            RunState::GameOver => {
+                if gui::game_over(&self.ecs, context) == gui::GameOverResult::NewGame {
+                    self.new_run();
+                    return;
+                }
             }
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, context);
@@ -114,11 +236,14 @@ impl GameState for State {
                         let item_entity = result.1.unwrap();
                         let is_ranged = self.ecs.read_storage::<Ranged>();
                         let is_item_ranged = is_ranged.get(item_entity);
-                        if let Some(is_item_ranged) = is_item_ranged {
+                        if self.ecs.read_storage::<Container>().get(item_entity).is_some() {
+                            newrunstate = RunState::ShowBag{ bag: item_entity };
+                        } else if let Some(is_item_ranged) = is_item_ranged {
                             newrunstate = RunState::ShowTargeting{ range: is_item_ranged.range, item: item_entity };
                         } else {
                             let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                             intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem{ item: item_entity, target: None }).expect("Unable to insert intent");
+                            initiative_system::spend_player_energy(&self.ecs, ActionType::UseItem);
                             newrunstate = RunState::PlayerTurn;
                         }
                     }
@@ -131,8 +256,95 @@ impl GameState for State {
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let item_entity = result.1.unwrap();
-                        let mut intent = self.ecs.write_storage::<WantsToDropItem>();
-                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToDropItem{ item: item_entity }).expect("Unable to insert intent");
+                        let stack_size = self.ecs.read_storage::<Quantity>().get(item_entity).map_or(1, |q| q.amount);
+                        if stack_size > 1 {
+                            newrunstate = RunState::ShowDropQuantity{ item: item_entity, amount: 0 };
+                        } else {
+                            newrunstate = player::drop_item(&mut self.ecs, item_entity);
+                        }
+                    }
+                }
+            }
+            RunState::ShowDropQuantity{item, amount} => {
+                let result = gui::drop_quantity_prompt(self, context, item, amount);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => newrunstate = RunState::ShowDropQuantity{ item, amount: result.1 },
+                    gui::ItemMenuResult::Selected => {
+                        let stack_size = self.ecs.read_storage::<Quantity>().get(item).map_or(1, |q| q.amount);
+                        // Nothing typed, or more than there are, drops the lot
+                        let to_drop = if result.1 > 0 && result.1 < stack_size {
+                            inventory_system::split_stack(&mut self.ecs, item, result.1)
+                        } else {
+                            item
+                        };
+                        newrunstate = player::drop_item(&mut self.ecs, to_drop);
+                    }
+                }
+            }
+            RunState::ShowBag{bag} => {
+                let result = gui::show_bag(self, context, bag);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => newrunstate = player::retrieve_item(&mut self.ecs, result.1.unwrap())
+                }
+            }
+            RunState::ShowPutAway => {
+                let result = gui::put_away_menu(self, context);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let item_entity = result.1.unwrap();
+                        // No need to ask which container when there's only the one
+                        let containers = gui::player_containers(&self.ecs);
+                        if containers.len() == 1 {
+                            newrunstate = player::store_item(&mut self.ecs, item_entity, containers[0].0);
+                        } else {
+                            newrunstate = RunState::ShowChooseContainer{ item: item_entity };
+                        }
+                    }
+                }
+            }
+            RunState::ShowChooseContainer{item} => {
+                let result = gui::choose_container_menu(self, context);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => newrunstate = player::store_item(&mut self.ecs, item, result.1.unwrap())
+                }
+            }
+            RunState::ShowCastSpell => {
+                let result = gui::cast_spell_menu(self, context);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => newrunstate = player::cast_spell(&mut self.ecs, result.1.unwrap())
+                }
+            }
+            RunState::ShowThrowItem => {
+                let result = gui::throw_item_menu(self, context);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let item_entity = result.1.unwrap();
+                        let throwables = self.ecs.read_storage::<Throwable>();
+                        let range = throwables.get(item_entity).unwrap().range;
+                        newrunstate = RunState::ShowFireTargeting{ range, item: item_entity };
+                    }
+                }
+            }
+            RunState::ShowFireTargeting{range, item} => {
+                let result = gui::ranged_target(self, context, range);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let mut intent = self.ecs.write_storage::<WantsToFire>();
+                        intent.insert(*self.ecs.fetch::<Entity>(), WantsToFire{ item, target: result.1.unwrap() }).expect("Unable to insert intent");
+                        initiative_system::spend_player_energy(&self.ecs, ActionType::Fire);
                         newrunstate = RunState::PlayerTurn;
                     }
                 }
@@ -145,6 +357,7 @@ impl GameState for State {
                     gui::ItemMenuResult::Selected => {
                         let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                         intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem{ item, target: result.1 }).expect("Unable to insert intent");
+                        initiative_system::spend_player_energy(&self.ecs, ActionType::UseItem);
                         newrunstate = RunState::PlayerTurn;
                     }
                 }
@@ -169,43 +382,11 @@ fn main() -> rltk::BError {
     let mut gamestate = State {
         ecs: World::new()
     };
-    gamestate.ecs.register::<Position>();
-    gamestate.ecs.register::<Renderable>();
-    gamestate.ecs.register::<Player>();
-    gamestate.ecs.register::<Viewshed>();
-    gamestate.ecs.register::<Monster>();
-    gamestate.ecs.register::<Name>();
-    gamestate.ecs.register::<BlocksTile>();
-    gamestate.ecs.register::<CombatStats>();
-    gamestate.ecs.register::<WantsToMelee>();
-    gamestate.ecs.register::<SufferDamage>();
-    gamestate.ecs.register::<Item>();
-    gamestate.ecs.register::<ProvidesHealing>();
-    gamestate.ecs.register::<InflictsDamage>();
-    gamestate.ecs.register::<AreaOfEffect>();
-    gamestate.ecs.register::<Consumable>();
-    gamestate.ecs.register::<Ranged>();
-    gamestate.ecs.register::<InBackpack>();
-    gamestate.ecs.register::<WantsToPickupItem>();
-    gamestate.ecs.register::<WantsToUseItem>();
-    gamestate.ecs.register::<WantsToDropItem>();
-    gamestate.ecs.register::<Confusion>();
-
-    let map : Map = Map::random_room_dungeon();
-    let (player_x, player_y) = map.rooms[0].center();
-
-    let player_entity = spawner::player(&mut gamestate.ecs, player_x, player_y);
+    macro_rules! register { ($($component:ty),*) => { $( gamestate.ecs.register::<$component>(); )* }; }
+    all_components!(register);
 
     gamestate.ecs.insert(rltk::RandomNumberGenerator::new());
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gamestate.ecs, room);
-    }
-
-    gamestate.ecs.insert(map);
-    gamestate.ecs.insert(Point::new(player_x, player_y));
-    gamestate.ecs.insert(player_entity);
-    gamestate.ecs.insert(RunState::PreRun);
-    gamestate.ecs.insert(gamelog::GameLog{ entries : vec!["Welcome to Rusty Roguelike".to_string()] });
+    gamestate.new_run();
 
     rltk::main_loop(context, gamestate)
 }
\ No newline at end of file
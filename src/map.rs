@@ -21,7 +21,8 @@ pub struct Map {
     pub revealed_tiles : Vec<bool>,
     pub visible_tiles : Vec<bool>,
     pub blocked : Vec<bool>,
-    pub tile_content : Vec<Vec<Entity>>
+    pub tile_content : Vec<Vec<Entity>>,
+    pub depth : i32
 }
 
 impl Map {
@@ -42,7 +43,7 @@ impl Map {
         for x in min(x1,x2) ..= max(x1,x2) {
             let index = self.xy_index(x, y);
             if index > 0 && index < self.width as usize * self.height as usize {
-                self.tiles[index as usize] = TileType::Floor;
+                self.tiles[index] = TileType::Floor;
             }
         }
     }
@@ -51,7 +52,7 @@ impl Map {
         for y in min(y1,y2) ..= max(y1,y2) {
             let index = self.xy_index(x, y);
             if index > 0 && index < self.width as usize * self.height as usize {
-                self.tiles[index as usize] = TileType::Floor;
+                self.tiles[index] = TileType::Floor;
             }
         }
     }
@@ -85,7 +86,8 @@ impl Map {
             revealed_tiles : vec![false; MAPCOUNT],
             visible_tiles : vec![false; MAPCOUNT],
             blocked : vec![false; MAPCOUNT],
-            tile_content : vec![Vec::new(); MAPCOUNT]
+            tile_content : vec![Vec::new(); MAPCOUNT],
+            depth : 1
         };
 
         const MAX_ROOMS : i32 = 30;
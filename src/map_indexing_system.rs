@@ -1,5 +1,5 @@
 use specs::prelude::*;
-use super::{Map, Position, BlocksTile};
+use super::{Map, Position, BlocksTile, ParticleLifetime};
 
 pub struct MapIndexingSystem {}
 
@@ -7,14 +7,16 @@ impl<'a> System<'a> for MapIndexingSystem {
     type SystemData = ( WriteExpect<'a, Map>,
                         ReadStorage<'a, Position>,
                         ReadStorage<'a, BlocksTile>,
+                        ReadStorage<'a, ParticleLifetime>,
                         Entities<'a>,);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, particles, entities) = data;
 
         map.populate_blocked();
         map.clear_content_index();
-        for (entity, position) in (&entities, &position).join() {
+        // Particles are purely visual, so they never occupy a tile
+        for (entity, position, _not_particle) in (&entities, &position, !&particles).join() {
             let index = map.xy_index(position.x, position.y);
 
             // If they block, update the blocking list
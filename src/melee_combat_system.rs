@@ -1,35 +1,194 @@
 use specs::prelude::*;
-use super::{CombatStats, WantsToMelee, Name, SufferDamage, gamelog::GameLog};
+use rltk::{RandomNumberGenerator, RGB};
+use super::{CombatStats, DamageType, WantsToMelee, Name, SufferDamage, DamageDice, Equipped,
+            EquipmentSlot, MeleePowerBonus, DefenseBonus, InflictsStatus, StatusEffects, Attributes, Skills,
+            Position, Knockback, ForcedMovement, Unaware, Durability, Metal, Corrodes, gamesystem, particle_system::ParticleBuilder,
+            durability_system::{WearQueue, USE_WEAR},
+            stealth_system::{NoiseQueue, FIGHT_NOISE}, game_events::{GameEvents, GameEvent}};
+
+/// Extra to-hit against a target that hasn't noticed its attacker.
+const SNEAK_ATTACK_BONUS : i32 = 4;
+
+/// Used by anything that attacks without its own `DamageDice`.
+pub const UNARMED : DamageDice = DamageDice{ n_dice: 1, die_type: 4, bonus: 0 };
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum AttackResult { Fumble, Miss, Hit(i32), Critical(i32) }
+
+/// Resolves a single attack from an already rolled d20 and damage dice total.
+/// A natural 1 always fumbles and a natural 20 always lands, doubling the dice.
+/// Otherwise the attack hits if `natural_roll + attack_bonus` meets the armour class.
+/// Any hit does at least 1 damage.
+pub fn resolve_attack(natural_roll : i32, attack_bonus : i32, armour_class : i32, dice_total : i32, damage_bonus : i32) -> AttackResult {
+    match natural_roll {
+        1 => AttackResult::Fumble,
+        20 => AttackResult::Critical(i32::max(1, dice_total * 2 + damage_bonus)),
+        _ if natural_roll + attack_bonus >= armour_class => AttackResult::Hit(i32::max(1, dice_total + damage_bonus)),
+        _ => AttackResult::Miss
+    }
+}
 
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = ( Entities<'a>,
-                        WriteExpect<'a, GameLog>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteExpect<'a, RandomNumberGenerator>,
                         WriteStorage<'a, WantsToMelee>,
                         ReadStorage<'a, Name>,
                         ReadStorage<'a, CombatStats>,
-                        WriteStorage<'a, SufferDamage>
+                        ReadStorage<'a, DamageDice>,
+                        WriteStorage<'a, SufferDamage>,
+                        ReadStorage<'a, Equipped>,
+                        ReadStorage<'a, MeleePowerBonus>,
+                        ReadStorage<'a, DefenseBonus>,
+                        ReadStorage<'a, InflictsStatus>,
+                        WriteStorage<'a, StatusEffects>,
+                        ReadStorage<'a, Attributes>,
+                        ReadStorage<'a, Skills>,
+                        ReadStorage<'a, Position>,
+                        WriteExpect<'a, ParticleBuilder>,
+                        ReadStorage<'a, Knockback>,
+                        WriteStorage<'a, ForcedMovement>,
+                        ReadStorage<'a, Unaware>,
+                        WriteExpect<'a, NoiseQueue>,
+                        ReadStorage<'a, Durability>,
+                        ReadStorage<'a, Metal>,
+                        ReadStorage<'a, Corrodes>,
+                        WriteExpect<'a, WearQueue>
                       );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (entities, mut log, mut wants_melee, names, combat_stats, mut inflict_damage) = data;
+        let (entities, mut events, mut rng, mut wants_melee, names, combat_stats, damage_dice, mut inflict_damage,
+            equipped, melee_power_bonuses, defense_bonuses, inflicts_status, mut statuses, attributes, skills,
+            positions, mut particles, knockbacks, mut forced_movement,
+            unaware, mut noise, durability, metal, corrodes, mut wear) = data;
 
-        for (_entity, wants_melee, name, stats) in (&entities, &wants_melee, &names, &combat_stats).join() {
+        for (entity, wants_melee, _name, _stats) in (&entities, &wants_melee, &names, &combat_stats).join() {
 
-            let target_stats = combat_stats.get(wants_melee.target).unwrap();
-            let target_name = names.get(wants_melee.target).unwrap();
-            let damage = i32::max(0, stats.power - target_stats.defense);
+            let attacker_attributes = attributes.get(entity).cloned().unwrap_or_default();
+            let attacker_skills = skills.get(entity).cloned().unwrap_or_default();
+            let target_attributes = attributes.get(wants_melee.target).cloned().unwrap_or_default();
+            let target_skills = skills.get(wants_melee.target).cloned().unwrap_or_default();
 
-            if damage == 0 {
-                log.entries.push(format!("{} is unable to hurt {}", &name.name, &target_name.name));
+            let mut dice = *damage_dice.get(entity).unwrap_or(&UNARMED);
+            let mut knockback = knockbacks.get(entity).map(|k| k.distance);
+            let mut offensive_bonus = 0;
+            let mut defensive_bonus = 0;
+            let mut weapon : Option<Entity> = None;
+            let mut target_metal : Vec<Entity> = Vec::new();
+            for (item_entity, equipped_by) in (&entities, &equipped).join() {
+                // Broken gear is no help to anyone
+                if durability.get(item_entity).is_some_and(|d| d.broken()) { continue; }
+                if equipped_by.owner == entity {
+                    if let Some(power_bonus) = melee_power_bonuses.get(item_entity) {
+                        offensive_bonus += power_bonus.power;
+                    }
+                    if equipped_by.slot == EquipmentSlot::Melee {
+                        weapon = Some(item_entity);
+                        if let Some(weapon_dice) = damage_dice.get(item_entity) {
+                            dice = *weapon_dice;
+                        }
+                        if let Some(weapon_knockback) = knockbacks.get(item_entity) {
+                            knockback = Some(weapon_knockback.distance);
+                        }
+                    }
+                }
+                if equipped_by.owner == wants_melee.target {
+                    if let Some(defense_bonus) = defense_bonuses.get(item_entity) {
+                        defensive_bonus += defense_bonus.defense;
+                    }
+                    if metal.get(item_entity).is_some() {
+                        target_metal.push(item_entity);
+                    }
+                }
+            }
+
+            let natural_roll = rng.roll_dice(1, 20);
+            let dice_total = rng.roll_dice(dice.n_dice, dice.die_type);
+            let mut attack_bonus = gamesystem::melee_attack_bonus(&attacker_attributes, &attacker_skills) + offensive_bonus;
+            if unaware.get(wants_melee.target).is_some() {
+                attack_bonus += SNEAK_ATTACK_BONUS;
+            }
+            if let Some(pos) = positions.get(entity) {
+                noise.make(pos.x, pos.y, FIGHT_NOISE);
+            }
+            let armour_class = gamesystem::armour_class(&target_attributes, &target_skills) + defensive_bonus;
+            let damage_bonus = dice.bonus + gamesystem::melee_damage_bonus(&attacker_attributes);
+
+            let result = resolve_attack(natural_roll, attack_bonus, armour_class, dice_total, damage_bonus);
+            let hit = matches!(result, AttackResult::Hit(_) | AttackResult::Critical(_));
+            if hit && corrodes.get(entity).is_some() {
+                // A corrosive touch rusts the target's metal gear instead of hurting them
+                let item = match target_metal.len() {
+                    0 => None,
+                    n => Some(target_metal[rng.roll_dice(1, n as i32) as usize - 1])
+                };
+                if let Some(item) = item {
+                    wear.corrode(item);
+                }
+                events.publish(GameEvent::CorrosiveTouch{ attacker: entity, target: wants_melee.target, item });
             } else {
-                log.entries.push(format!("{} hits {}, for {} hp.", &name.name, &target_name.name, damage));
-                SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                events.publish(GameEvent::Attack{ attacker: entity, target: wants_melee.target, result });
+                if let AttackResult::Hit(damage) | AttackResult::Critical(damage) = result {
+                    SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage, DamageType::Physical, Some(entity));
+                    wear.strike(wants_melee.target);
+                    if let Some(weapon) = weapon {
+                        // Striking something corrosive with metal rusts it
+                        if corrodes.get(wants_melee.target).is_some() && metal.get(weapon).is_some() {
+                            wear.corrode(weapon);
+                            events.publish(GameEvent::CorrosiveTouch{ attacker: wants_melee.target, target: entity, item: Some(weapon) });
+                        } else {
+                            wear.wear(weapon, USE_WEAR);
+                        }
+                    }
+                }
+            }
+
+            // Venomous bites and the like pass their status on with a hit, and heavy blows knock foes back
+            if hit {
+                if let (Some(distance), Some(pos)) = (knockback, positions.get(entity)) {
+                    forced_movement.insert(wants_melee.target, ForcedMovement{ origin: rltk::Point::new(pos.x, pos.y), distance, source: Some(entity) })
+                        .expect("Unable to insert forced movement");
+                }
+                if let Some(pos) = positions.get(wants_melee.target) {
+                    particles.request(pos.x, pos.y, RGB::named(rltk::ORANGE), rltk::to_cp437('‼'), 200.0);
+                }
+                if let Some(inflicts) = inflicts_status.get(entity) {
+                    StatusEffects::add_effect(&mut statuses, wants_melee.target, inflicts.effect());
+                    events.publish(GameEvent::StatusInflicted{ source: entity, target: wants_melee.target, item: None, kind: inflicts.kind });
+                }
             }
         }
 
         wants_melee.clear();
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_one_always_fumbles() {
+        assert_eq!(resolve_attack(1, 100, 0, 6, 2), AttackResult::Fumble);
+    }
+
+    #[test]
+    fn natural_twenty_always_crits_and_doubles_dice() {
+        assert_eq!(resolve_attack(20, -100, 100, 4, 2), AttackResult::Critical(10));
+    }
+
+    #[test]
+    fn hits_when_roll_meets_armour_class() {
+        assert_eq!(resolve_attack(10, 5, 15, 3, 2), AttackResult::Hit(5));
+        assert_eq!(resolve_attack(9, 5, 15, 3, 2), AttackResult::Miss);
+    }
+
+    #[test]
+    fn hits_always_do_at_least_one_damage() {
+        assert_eq!(resolve_attack(15, 0, 10, 1, -5), AttackResult::Hit(1));
+        assert_eq!(resolve_attack(20, 0, 10, 1, -5), AttackResult::Critical(1));
+    }
+}
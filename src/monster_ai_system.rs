@@ -1,6 +1,10 @@
 use specs::prelude::*;
-use super::{Viewshed, Monster, Map, Position, WantsToMelee, RunState, Confusion};
-use rltk::{Point};
+use super::{Viewshed, Monster, Map, Position, WantsToMelee, RunState, StatusEffects, StatusKind, Initiative, Unaware, Morale, Follower,
+            ActionType, status_effect_system::can_act, initiative_system::ACTION_THRESHOLD};
+use rltk::{Point, RandomNumberGenerator, DijkstraMap};
+
+/// How far out fleeing monsters look for somewhere away from the player.
+const FLEE_DEPTH : f32 = 20.0;
 
 pub struct MonsterAI {}
 
@@ -10,53 +14,107 @@ impl<'a> System<'a> for MonsterAI {
                         ReadExpect<'a, Point>,
                         ReadExpect<'a, Entity>,
                         ReadExpect<'a, RunState>,
+                        WriteExpect<'a, RandomNumberGenerator>,
                         Entities<'a>,
                         WriteStorage<'a, Viewshed>,
                         ReadStorage<'a, Monster>,
                         WriteStorage<'a, Position>,
                         WriteStorage<'a, WantsToMelee>,
-                        WriteStorage<'a, Confusion>);
+                        ReadStorage<'a, StatusEffects>,
+                        WriteStorage<'a, Initiative>,
+                        ReadStorage<'a, Unaware>,
+                        ReadStorage<'a, Morale>,
+                        ReadStorage<'a, Follower>);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut map, player_pos, player_entity, runstate, entities, mut viewshed, monster, mut position, mut wants_to_melee, mut confused) = data;
+        let (mut map, player_pos, player_entity, runstate, mut rng, entities, mut viewshed, monster, mut position, mut wants_to_melee, statuses, mut initiatives, unaware, morale, followers) = data;
 
-        if *runstate != RunState::MonsterTurn { return; }
+        if *runstate != RunState::Ticking { return; }
 
-        for (entity, mut viewshed,_monster,mut pos) in (&entities, &mut viewshed, &monster, &mut position).join() {
-            let mut can_act = true;
+        let allies : Vec<(Entity, Point)> = (&entities, &followers, &position).join()
+            .map(|(entity, _, pos)| (entity, Point::new(pos.x, pos.y)))
+            .collect();
 
-            let is_confused = confused.get_mut(entity);
-            if let Some(i_am_confused) = is_confused {
-                i_am_confused.turns -= 1;
-                if i_am_confused.turns < 1 {
-                    confused.remove(entity);
-                }
-                can_act = false;
+        // Only worked out if somebody is running away
+        let mut flee_map : Option<DijkstraMap> = None;
+
+        for (entity, viewshed,_monster,pos, initiative) in (&entities, &mut viewshed, &monster, &mut position, &mut initiatives).join() {
+            if initiative.energy < ACTION_THRESHOLD { continue; }
+
+            let my_statuses = statuses.get(entity);
+            // Monsters that haven't noticed the player yet stay put
+            if !can_act(my_statuses) || unaware.get(entity).is_some() {
+                initiative.spend(ActionType::Wait);
+                continue;
             }
 
-            if can_act {
-                let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-                if distance < 1.5 {
-                    wants_to_melee.insert(entity, WantsToMelee{ target: *player_entity }).expect("Unable to insert attack");
-                }
-                else if viewshed.visible_tiles.contains(&*player_pos) {
-                    // Path to the player
-                    let path = rltk::a_star_search(
-                        map.xy_index(pos.x, pos.y),
-                        map.xy_index(player_pos.x, player_pos.y),
-                        &*map
-                    );
-                    if path.success && path.steps.len()>1 {
-                        let mut index = map.xy_index(pos.x, pos.y);
-                        map.blocked[index] = false;
-                        pos.x = path.steps[1] as i32 % map.width;
-                        pos.y = path.steps[1] as i32 / map.width;
-                        index = map.xy_index(pos.x, pos.y);
+            if my_statuses.is_some_and(|s| s.has(StatusKind::Confusion)) {
+                initiative.spend(ActionType::Move);
+                // Stagger in a random direction
+                let x = pos.x + rng.range(-1, 2);
+                let y = pos.y + rng.range(-1, 2);
+                if x > 0 && x < map.width-1 && y > 0 && y < map.height-1 {
+                    let index = map.xy_index(x, y);
+                    if !map.blocked[index] {
+                        let old_index = map.xy_index(pos.x, pos.y);
+                        map.blocked[old_index] = false;
+                        pos.x = x;
+                        pos.y = y;
                         map.blocked[index] = true;
                         viewshed.dirty = true;
                     }
                 }
+                continue;
+            }
+
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+
+            // Fleeing monsters head for wherever is furthest from the player, and only fight when cornered
+            if morale.get(entity).is_some_and(|m| m.fleeing) {
+                let player_index = map.xy_index(player_pos.x, player_pos.y);
+                let flee_map = flee_map.get_or_insert_with(|| DijkstraMap::new(map.width, map.height, &[player_index], &*map, FLEE_DEPTH));
+                let index = map.xy_index(pos.x, pos.y);
+                let exit = DijkstraMap::find_highest_exit(flee_map, index, &*map);
+                if let Some(exit) = exit.filter(|exit| distance >= 1.5 || flee_map.map[*exit] > 1.5) {
+                    initiative.spend(ActionType::Move);
+                    map.blocked[index] = false;
+                    pos.x = exit as i32 % map.width;
+                    pos.y = exit as i32 / map.width;
+                    map.blocked[exit] = true;
+                    viewshed.dirty = true;
+                    continue;
+                }
+            }
+
+            if distance < 1.5 {
+                initiative.spend(ActionType::Attack);
+                wants_to_melee.insert(entity, WantsToMelee{ target: *player_entity }).expect("Unable to insert attack");
+            }
+            else if let Some((ally, _)) = allies.iter().find(|(_, ally_pos)| rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *ally_pos) < 1.5) {
+                // The player's allies get in the way
+                initiative.spend(ActionType::Attack);
+                wants_to_melee.insert(entity, WantsToMelee{ target: *ally }).expect("Unable to insert attack");
+            }
+            else if viewshed.visible_tiles.contains(&*player_pos) {
+                // Path to the player
+                initiative.spend(ActionType::Move);
+                let path = rltk::a_star_search(
+                    map.xy_index(pos.x, pos.y),
+                    map.xy_index(player_pos.x, player_pos.y),
+                    &*map
+                );
+                if path.success && path.steps.len()>1 {
+                    let mut index = map.xy_index(pos.x, pos.y);
+                    map.blocked[index] = false;
+                    pos.x = path.steps[1] as i32 % map.width;
+                    pos.y = path.steps[1] as i32 / map.width;
+                    index = map.xy_index(pos.x, pos.y);
+                    map.blocked[index] = true;
+                    viewshed.dirty = true;
+                }
+            } else {
+                initiative.spend(ActionType::Wait);
             }
         }
     }
-}
\ No newline at end of file
+}
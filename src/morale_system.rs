@@ -0,0 +1,114 @@
+use specs::prelude::*;
+use rltk::Point;
+use super::{Morale, Monster, Viewshed, Position, initiative_system::GameClock, game_events::{GameEvents, GameEvent}};
+
+/// Morale a typical monster starts with.
+pub const BASE_MORALE : i32 = 10;
+/// Lost on every hit taken while below a third of full health.
+pub const BADLY_HURT_LOSS : i32 = 5;
+/// Lost on seeing another monster die.
+pub const ALLY_DEATH_LOSS : i32 = 3;
+/// Monsters flee below this...
+pub const FLEE_BELOW : i32 = 4;
+/// ...and come back once they have recovered to this, at one point per turn.
+pub const RALLY_AT : i32 = 8;
+
+/// Anything with a `Morale` that was in sight of one of the dead loses heart.
+pub fn witness_deaths(ecs : &World, dead : &[Entity]) {
+    let positions = ecs.read_storage::<Position>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let monsters = ecs.read_storage::<Monster>();
+    let mut morale = ecs.write_storage::<Morale>();
+    let entities = ecs.entities();
+
+    for victim in dead.iter().filter(|victim| monsters.get(**victim).is_some()) {
+        let Some(pos) = positions.get(*victim) else { continue; };
+        let victim_pos = Point::new(pos.x, pos.y);
+        for (entity, morale, viewshed) in (&entities, &mut morale, &viewsheds).join() {
+            if entity != *victim && viewshed.visible_tiles.contains(&victim_pos) {
+                morale.value -= ALLY_DEATH_LOSS;
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum MoraleChange { Flees, Rallies }
+
+/// Breaks a monster whose morale has dropped too low, or lets a fleeing one recover a point
+/// if a new turn has begun and rally once it has enough nerve back.
+pub fn check_nerve(morale : &mut Morale, new_turn : bool) -> Option<MoraleChange> {
+    if morale.fleeing {
+        if new_turn {
+            morale.value = i32::min(morale.max, morale.value + 1);
+        }
+        if morale.value >= RALLY_AT {
+            morale.fleeing = false;
+            return Some(MoraleChange::Rallies);
+        }
+    } else if morale.value < FLEE_BELOW {
+        morale.fleeing = true;
+        return Some(MoraleChange::Flees);
+    }
+    None
+}
+
+/// Decides who breaks and runs, and lets fleeing monsters recover their nerve over time.
+pub struct MoraleSystem {}
+
+impl<'a> System<'a> for MoraleSystem {
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, GameClock>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteStorage<'a, Morale> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, clock, mut events, mut morale) = data;
+
+        for (entity, morale) in (&entities, &mut morale).join() {
+            match check_nerve(morale, clock.ticked) {
+                Some(MoraleChange::Rallies) => events.publish(GameEvent::Rallied{ entity }),
+                Some(MoraleChange::Flees) => events.publish(GameEvent::Fled{ entity }),
+                None => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn morale(value : i32, fleeing : bool) -> Morale {
+        Morale{ value, max: BASE_MORALE, fleeing }
+    }
+
+    #[test]
+    fn monsters_flee_once_below_the_threshold() {
+        let mut steady = morale(FLEE_BELOW, false);
+        assert_eq!(check_nerve(&mut steady, true), None);
+        assert!(!steady.fleeing);
+
+        let mut shaken = morale(FLEE_BELOW - 1, false);
+        assert_eq!(check_nerve(&mut shaken, true), Some(MoraleChange::Flees));
+        assert!(shaken.fleeing);
+    }
+
+    #[test]
+    fn fleeing_monsters_recover_a_point_per_turn_and_rally() {
+        let mut fleeing = morale(RALLY_AT - 2, true);
+        assert_eq!(check_nerve(&mut fleeing, true), None);
+        assert_eq!(check_nerve(&mut fleeing, false), None);
+        assert_eq!(fleeing.value, RALLY_AT - 1);
+        assert_eq!(check_nerve(&mut fleeing, true), Some(MoraleChange::Rallies));
+        assert!(!fleeing.fleeing);
+    }
+
+    #[test]
+    fn recovery_stops_at_maximum_morale() {
+        let mut timid = Morale{ value: 2, max: 3, fleeing: true };
+        for _ in 0..5 { assert_eq!(check_nerve(&mut timid, true), None); }
+        assert_eq!(timid.value, 3);
+        assert!(timid.fleeing);
+    }
+}
@@ -0,0 +1,135 @@
+use specs::prelude::*;
+use std::fmt::Write;
+use super::{CombatStats, Name, InBackpack, Equipped, Map, TileType, Position, Renderable, Experience,
+            Attributes, Skills, ParticleLifetime, Quantity, Container,
+            gamelog::GameLog, initiative_system::GameClock, game_events::GameEvent};
+
+pub const MORGUE_FILE : &str = "morgue.txt";
+
+/// How many of the most recent log messages go in the morgue file.
+const MORGUE_MESSAGES : usize = 20;
+
+/// Running tally of the current game, shown on the death screen and in the morgue file.
+#[derive(Default)]
+pub struct RunStats {
+    pub kills : i32,
+    pub cause_of_death : Option<String>
+}
+
+/// Subscriber that keeps the run's tally up to date.
+pub fn count_event(ecs : &World, event : &GameEvent) {
+    if let GameEvent::EntityDied{ killer: Some(killer), .. } = event {
+        if *killer == *ecs.fetch::<Entity>() {
+            ecs.write_resource::<RunStats>().kills += 1;
+        }
+    }
+}
+
+/// Builds the plain-text record of a finished run.
+pub fn morgue_text(ecs : &World) -> String {
+    let player_entity = ecs.fetch::<Entity>();
+    let stats = ecs.fetch::<RunStats>();
+    let clock = ecs.fetch::<GameClock>();
+    let map = ecs.fetch::<Map>();
+    let log = ecs.fetch::<GameLog>();
+    let names = ecs.read_storage::<Name>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let experience = ecs.read_storage::<Experience>();
+    let particles = ecs.read_storage::<ParticleLifetime>();
+    let attributes = ecs.read_storage::<Attributes>();
+    let skills = ecs.read_storage::<Skills>();
+    let quantities = ecs.read_storage::<Quantity>();
+    let containers = ecs.read_storage::<Container>();
+    let entities = ecs.entities();
+
+    let mut text = String::new();
+    writeln!(text, "Rusty Roguelike morgue file").unwrap();
+    writeln!(text, "===========================").unwrap();
+    writeln!(text).unwrap();
+    if let Some(cause) = &stats.cause_of_death {
+        writeln!(text, "{} on level {}.", cause, map.depth).unwrap();
+    }
+    writeln!(text, "Survived {} turns and slew {} monsters.", clock.turns, stats.kills).unwrap();
+    writeln!(text).unwrap();
+
+    writeln!(text, "Character").unwrap();
+    writeln!(text, "---------").unwrap();
+    if let Some(exp) = experience.get(*player_entity) {
+        writeln!(text, "Level: {} ({} / {} xp)", exp.level, exp.xp, exp.xp_to_next_level()).unwrap();
+    }
+    if let Some(player_stats) = combat_stats.get(*player_entity) {
+        writeln!(text, "HP: {} / {}", player_stats.hp, player_stats.max_hp).unwrap();
+    }
+    if let Some(attr) = attributes.get(*player_entity) {
+        writeln!(text, "Might: {}  Fitness: {}  Quickness: {}  Intelligence: {}", attr.might, attr.fitness, attr.quickness, attr.intelligence).unwrap();
+    }
+    if let Some(skill) = skills.get(*player_entity) {
+        writeln!(text, "Skills: melee {}, defense {}, magic {}, stealth {}", skill.melee, skill.defense, skill.magic, skill.stealth).unwrap();
+    }
+    writeln!(text).unwrap();
+
+    writeln!(text, "Inventory").unwrap();
+    writeln!(text, "---------").unwrap();
+    let held_by = |holder : Entity| -> Vec<(Entity, String)> {
+        (&entities, &backpack, &names, quantities.maybe()).join()
+            .filter(|item| item.1.owner == holder)
+            .map(|(entity, _pack, name, quantity)| match (equipped.get(entity), quantity) {
+                (Some(equipped), _) => (entity, format!("{} ({})", name.name, equipped.slot)),
+                (None, Some(quantity)) if quantity.amount > 1 => (entity, format!("{} (x{})", name.name, quantity.amount)),
+                (None, _) => (entity, name.name.clone())
+            })
+            .collect()
+    };
+    for (entity, line) in held_by(*player_entity) {
+        writeln!(text, "  {}", line).unwrap();
+        // Anything kept in a container is listed under it
+        if containers.get(entity).is_some() {
+            for (_, inner) in held_by(entity) {
+                writeln!(text, "    {}", inner).unwrap();
+            }
+        }
+    }
+    writeln!(text).unwrap();
+
+    writeln!(text, "Last messages").unwrap();
+    writeln!(text, "-------------").unwrap();
+    let first = log.entries.len().saturating_sub(MORGUE_MESSAGES);
+    for entry in log.entries.iter().skip(first) {
+        writeln!(text, "  {}", entry).unwrap();
+    }
+    writeln!(text).unwrap();
+
+    writeln!(text, "Map").unwrap();
+    writeln!(text, "---").unwrap();
+    let mut rows : Vec<Vec<char>> = (0..map.height).map(|y| {
+        (0..map.width).map(|x| {
+            let index = map.xy_index(x, y);
+            if !map.revealed_tiles[index] { return ' '; }
+            match map.tiles[index] {
+                TileType::Floor => '.',
+                TileType::Wall => '#'
+            }
+        }).collect()
+    }).collect();
+    let mut drawn = (&positions, &renderables, !&particles).join().collect::<Vec<_>>();
+    drawn.sort_by_key(|&(_, r, _)| std::cmp::Reverse(r.render_order));
+    for (pos, render, _) in drawn.iter() {
+        let index = map.xy_index(pos.x, pos.y);
+        if map.visible_tiles[index] {
+            rows[pos.y as usize][pos.x as usize] = char::from_u32(render.glyph as u32).filter(|c| c.is_ascii()).unwrap_or('?');
+        }
+    }
+    for row in rows.iter() {
+        writeln!(text, "{}", row.iter().collect::<String>().trim_end()).unwrap();
+    }
+
+    text
+}
+
+pub fn write_morgue_file(ecs : &World) -> std::io::Result<()> {
+    std::fs::write(MORGUE_FILE, morgue_text(ecs))
+}
@@ -0,0 +1,72 @@
+use specs::prelude::*;
+use rltk::{Rltk, RGB};
+use super::{ParticleLifetime, Position, Renderable};
+
+/// A particle waiting to be spawned. `delay_ms` holds it back so that effects like
+/// an expanding blast can be staged over a few frames.
+pub struct ParticleRequest {
+    pub x : i32,
+    pub y : i32,
+    pub foreground : RGB,
+    pub background : RGB,
+    pub glyph : rltk::FontCharType,
+    pub lifetime_ms : f32,
+    pub delay_ms : f32
+}
+
+/// Queue of pending particles. Systems add to it; `update_particles` turns them into
+/// entities once per frame.
+#[derive(Default)]
+pub struct ParticleBuilder {
+    requests : Vec<ParticleRequest>
+}
+
+impl ParticleBuilder {
+    pub fn new() -> ParticleBuilder {
+        ParticleBuilder::default()
+    }
+
+    pub fn request(&mut self, x : i32, y : i32, foreground : RGB, glyph : rltk::FontCharType, lifetime_ms : f32) {
+        self.push(ParticleRequest{ x, y, foreground, background: RGB::named(rltk::BLACK), glyph, lifetime_ms, delay_ms: 0.0 });
+    }
+
+    pub fn push(&mut self, request : ParticleRequest) {
+        self.requests.push(request);
+    }
+}
+
+/// Called every frame from `State::tick`: ages live particles by the frame time, deletes the
+/// ones that have burned out and spawns any queued particles whose delay has run down.
+pub fn update_particles(ecs : &mut World, ctx : &Rltk) {
+    let mut dead_particles : Vec<Entity> = Vec::new();
+    {
+        let mut particles = ecs.write_storage::<ParticleLifetime>();
+        let entities = ecs.entities();
+        for (entity, particle) in (&entities, &mut particles).join() {
+            particle.lifetime_ms -= ctx.frame_time_ms;
+            if particle.lifetime_ms < 0.0 {
+                dead_particles.push(entity);
+            }
+        }
+    }
+    for dead in dead_particles.iter() {
+        ecs.delete_entity(*dead).expect("Particle will not die");
+    }
+
+    let ready : Vec<ParticleRequest> = {
+        let mut builder = ecs.write_resource::<ParticleBuilder>();
+        for request in builder.requests.iter_mut() {
+            request.delay_ms -= ctx.frame_time_ms;
+        }
+        let (ready, waiting) = builder.requests.drain(..).partition(|request| request.delay_ms <= 0.0);
+        builder.requests = waiting;
+        ready
+    };
+    for request in ready {
+        ecs.create_entity()
+            .with(Position{ x: request.x, y: request.y })
+            .with(Renderable{ glyph: request.glyph, foreground: request.foreground, background: request.background, render_order: 0 })
+            .with(ParticleLifetime{ lifetime_ms: request.lifetime_ms })
+            .build();
+    }
+}
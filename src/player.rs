@@ -1,10 +1,26 @@
-use rltk::{VirtualKeyCode, Rltk, Point};
+use rltk::{VirtualKeyCode, Rltk, Point, RandomNumberGenerator};
 use specs::prelude::*;
-use std::cmp::{max, min};
 use super::{Position, Player, Viewshed, State, Map, RunState, CombatStats, WantsToMelee, Item,
-            gamelog::GameLog, WantsToPickupItem};
+            gamelog::GameLog, WantsToPickupItem, Equipped, EquipmentSlot, RangedWeapon, Ammunition, InBackpack,
+            Name, StatusEffects, StatusKind, ActionType, Experience, Attributes, Skills, gamesystem, Sneaking, Mana, KnownSpells, Spell, Ranged, WantsToUseItem, WantsToDropItem, Follower, Encumbrance, Container, Scroll, WantsToStoreItem, WantsToRetrieveItem,
+            identification, inventory_system, initiative_system::spend_player_energy, stealth_system::{NoiseQueue, WALK_NOISE, SNEAK_NOISE},
+            game_events::{self, GameEvents, GameEvent}};
+
+pub fn try_move_player(mut delta_x: i32, mut delta_y: i32, ecs: &mut World) {
+    // Confused players stumble in a random direction
+    {
+        let statuses = ecs.read_storage::<StatusEffects>();
+        let player_entity = ecs.fetch::<Entity>();
+        if statuses.get(*player_entity).is_some_and(|s| s.has(StatusKind::Confusion)) {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            loop {
+                delta_x = rng.range(-1, 2);
+                delta_y = rng.range(-1, 2);
+                if delta_x != 0 || delta_y != 0 { break; }
+            }
+        }
+    }
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
@@ -12,23 +28,35 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     let combat_stats = ecs.read_storage::<CombatStats>();
     let map = ecs.fetch::<Map>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
-
+    let sneaking = ecs.read_storage::<Sneaking>();
+    let followers = ecs.read_storage::<Follower>();
+    let mut swap_with : Option<(Entity, Position)> = None;
 
     for (entity, _player, pos, viewshed) in (&entities, &players, &mut positions, &mut viewsheds).join() {
         if pos.x + delta_x < 1 || pos.x + delta_x > map.width-1 || pos.y + delta_y < 1 || pos.y + delta_y > map.height-1 { return; }
         let destination_index = map.xy_index(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_index].iter() {
+            if *potential_target == entity { continue; }
+            // Allies swap places rather than being attacked
+            if followers.get(*potential_target).is_some_and(|f| f.leader == entity) {
+                swap_with = Some((*potential_target, Position{ x: pos.x, y: pos.y }));
+                continue;
+            }
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee.insert(entity, WantsToMelee{ target: *potential_target }).expect("Add target failed");
+                spend_player_energy(ecs, ActionType::Attack);
                 return;
             }
         }
 
-        if !map.blocked[destination_index] {
-            pos.x = min(79 , max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+        if !map.blocked[destination_index] || swap_with.is_some() {
+            let sneaking = sneaking.get(entity).is_some();
+            spend_player_energy(ecs, if sneaking { ActionType::Sneak } else { ActionType::Move });
+            pos.x = (pos.x + delta_x).clamp(0, 79);
+            pos.y = (pos.y + delta_y).clamp(0, 49);
+            ecs.write_resource::<NoiseQueue>().make(pos.x, pos.y, if sneaking { SNEAK_NOISE } else { WALK_NOISE });
 
             viewshed.dirty = true;
             let mut ppos = ecs.write_resource::<Point>();
@@ -36,6 +64,16 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             ppos.y = pos.y;
         }
     }
+
+    if let Some((ally, destination)) = swap_with {
+        if let Some(ally_pos) = positions.get_mut(ally) {
+            *ally_pos = destination;
+        }
+        if let Some(viewshed) = viewsheds.get_mut(ally) {
+            viewshed.dirty = true;
+        }
+        ecs.write_resource::<GameEvents>().publish(GameEvent::SwappedPlaces{ entity: *ecs.fetch::<Entity>(), ally });
+    }
 }
 
 fn get_item(ecs: &mut World) {
@@ -44,6 +82,7 @@ fn get_item(ecs: &mut World) {
     let entities = ecs.entities();
     let items = ecs.read_storage::<Item>();
     let positions = ecs.read_storage::<Position>();
+    let encumbrance = ecs.read_storage::<Encumbrance>();
     let mut gamelog = ecs.fetch_mut::<GameLog>();
 
     let mut target_item : Option<Entity> = None;
@@ -56,10 +95,195 @@ fn get_item(ecs: &mut World) {
     match target_item {
         None => gamelog.entries.push("There is nothing here to pick up.".to_string()),
         Some(item) => {
+            let lbs = inventory_system::total_weight(ecs, item);
+            if encumbrance.get(*player_entity).is_some_and(|e| !e.can_carry(lbs)) {
+                gamelog.entries.push(format!("The {} is too heavy; you can't carry any more.", identification::display_name(ecs, item)));
+                return;
+            }
+
             let mut pickup = ecs.write_storage::<WantsToPickupItem>();
             pickup.insert(*player_entity, WantsToPickupItem{ collected_by: *player_entity, item }).expect("Unable to insert want to pickup");
+            spend_player_energy(ecs, ActionType::PickUp);
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum LevelUpChoice { Might, Fitness, Quickness, Intelligence, Melee, Defense, Magic, Stealth }
+
+pub fn can_level_up(ecs: &World) -> bool {
+    let experience = ecs.read_storage::<Experience>();
+    experience.get(*ecs.fetch::<Entity>()).is_some_and(|e| e.can_level_up())
+}
+
+/// Spends the experience for one level. Every level trains melee a little and re-derives
+/// max HP, on top of the player's chosen improvement; the player is restored to full health.
+pub fn level_up(ecs: &mut World, choice: LevelUpChoice) {
+    {
+        let player_entity = ecs.fetch::<Entity>();
+        let mut experience = ecs.write_storage::<Experience>();
+        let mut combat_stats = ecs.write_storage::<CombatStats>();
+        let mut attributes = ecs.write_storage::<Attributes>();
+        let mut skills = ecs.write_storage::<Skills>();
+        let mut mana = ecs.write_storage::<Mana>();
+        let mut events = ecs.write_resource::<GameEvents>();
+
+        if let (Some(exp), Some(stats), Some(attr), Some(skill)) = (experience.get_mut(*player_entity),
+                combat_stats.get_mut(*player_entity), attributes.get_mut(*player_entity), skills.get_mut(*player_entity)) {
+            exp.xp -= exp.xp_to_next_level();
+            exp.level += 1;
+            skill.melee += 1;
+            match choice {
+                LevelUpChoice::Might => attr.might += 1,
+                LevelUpChoice::Fitness => attr.fitness += 1,
+                LevelUpChoice::Quickness => attr.quickness += 1,
+                LevelUpChoice::Intelligence => attr.intelligence += 1,
+                LevelUpChoice::Melee => skill.melee += 1,
+                LevelUpChoice::Defense => skill.defense += 1,
+                LevelUpChoice::Magic => skill.magic += 1,
+                LevelUpChoice::Stealth => skill.stealth += 1
+            }
+            stats.max_hp = gamesystem::max_hp(attr, exp.level);
+            stats.hp = stats.max_hp;
+            if let Some(pool) = mana.get_mut(*player_entity) {
+                pool.max = gamesystem::max_mana(attr, skill, exp.level);
+                pool.current = pool.max;
+            }
+            events.publish(GameEvent::LevelledUp{ entity: *player_entity, level: exp.level });
+        }
+    }
+    game_events::dispatch(ecs);
+}
+
+/// Starts targeting with the player's readied ranged weapon, if they have one and something to fire from it.
+fn fire_weapon(ecs: &mut World) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let equipped = ecs.read_storage::<Equipped>();
+    let ranged_weapons = ecs.read_storage::<RangedWeapon>();
+    let ammunition = ecs.read_storage::<Ammunition>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let names = ecs.read_storage::<Name>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let weapon = (&entities, &equipped, &ranged_weapons).join()
+        .find(|(_, equipped_by, _)| equipped_by.owner == *player_entity && equipped_by.slot == EquipmentSlot::Ranged);
+    match weapon {
+        None => {
+            gamelog.entries.push("You have no ranged weapon readied.".to_string());
+            RunState::AwaitingInput
+        }
+        Some((weapon_entity, _, weapon)) => {
+            let has_ammo = (&ammunition, &backpack).join()
+                .any(|(ammo, pack)| pack.owner == *player_entity && ammo.ammo == weapon.ammo);
+            if has_ammo {
+                RunState::ShowFireTargeting{ range: weapon.range, item: weapon_entity }
+            } else {
+                gamelog.entries.push(format!("You have nothing to fire from the {}.", names.get(weapon_entity).unwrap().name));
+                RunState::AwaitingInput
+            }
+        }
+    }
+}
+
+fn knows_spells(ecs: &World) -> bool {
+    let known_spells = ecs.read_storage::<KnownSpells>();
+    known_spells.get(*ecs.fetch::<Entity>()).is_some_and(|known| !known.spells.is_empty())
+}
+
+/// Casts a spell picked from the spell menu: targeted spells go on to targeting, the rest
+/// are cast straight away. Nothing happens without enough mana.
+pub fn cast_spell(ecs: &mut World, spell_entity: Entity) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let spells = ecs.read_storage::<Spell>();
+    let mana = ecs.read_storage::<Mana>();
+    let ranged = ecs.read_storage::<Ranged>();
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+
+    let cost = spells.get(spell_entity).map(|s| s.mana_cost).unwrap_or(0);
+    if mana.get(*player_entity).is_none_or(|pool| pool.current < cost) {
+        gamelog.entries.push("You don't have enough mana.".to_string());
+        return RunState::AwaitingInput;
+    }
+
+    if let Some(range) = ranged.get(spell_entity) {
+        return RunState::ShowTargeting{ range: range.range, item: spell_entity };
+    }
+    let mut intent = ecs.write_storage::<WantsToUseItem>();
+    intent.insert(*player_entity, WantsToUseItem{ item: spell_entity, target: None }).expect("Unable to insert intent");
+    spend_player_energy(ecs, ActionType::UseItem);
+    RunState::PlayerTurn
+}
+
+/// Drops an item (or a whole stack) picked from the drop menu.
+pub fn drop_item(ecs: &mut World, item: Entity) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut intent = ecs.write_storage::<WantsToDropItem>();
+    intent.insert(*player_entity, WantsToDropItem{ item }).expect("Unable to insert intent");
+    spend_player_energy(ecs, ActionType::Drop);
+    RunState::PlayerTurn
+}
+
+/// Puts a carried item away in one of the player's containers, if it fits.
+pub fn store_item(ecs: &mut World, item: Entity, container: Entity) -> RunState {
+    let refusal = {
+        let containers = ecs.read_storage::<Container>();
+        let scrolls = ecs.read_storage::<Scroll>();
+        let item_name = identification::display_name(ecs, item);
+        let container_name = identification::display_name(ecs, container);
+        let Some(limits) = containers.get(container) else { return RunState::AwaitingInput };
+        if containers.get(item).is_some() {
+            Some(format!("The {} won't hold other containers.", container_name))
+        } else if limits.scrolls_only && scrolls.get(item).is_none() {
+            Some(format!("Only scrolls go in the {}.", container_name))
+        } else if inventory_system::contents_weight(ecs, container) + inventory_system::total_weight(ecs, item) > limits.max_weight {
+            Some(format!("The {} won't fit in the {}.", item_name, container_name))
+        } else {
+            None
         }
+    };
+    if let Some(refusal) = refusal {
+        ecs.fetch_mut::<GameLog>().entries.push(refusal);
+        return RunState::AwaitingInput;
     }
+
+    let player_entity = ecs.fetch::<Entity>();
+    let mut intent = ecs.write_storage::<WantsToStoreItem>();
+    intent.insert(*player_entity, WantsToStoreItem{ item, container }).expect("Unable to insert intent");
+    spend_player_energy(ecs, ActionType::PickUp);
+    RunState::PlayerTurn
+}
+
+fn has_container(ecs: &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let containers = ecs.read_storage::<Container>();
+    (&backpack, &containers).join().any(|(pack, _)| pack.owner == *player_entity)
+}
+
+/// Takes an item out of whichever container it's in and puts it back in the pack.
+pub fn retrieve_item(ecs: &mut World, item: Entity) -> RunState {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut intent = ecs.write_storage::<WantsToRetrieveItem>();
+    intent.insert(*player_entity, WantsToRetrieveItem{ item }).expect("Unable to insert intent");
+    spend_player_energy(ecs, ActionType::PickUp);
+    RunState::PlayerTurn
+}
+
+/// Sneaking is free to start or stop; it only makes each step slower and quieter.
+fn toggle_sneaking(ecs: &mut World) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let now_sneaking = {
+        let mut sneaking = ecs.write_storage::<Sneaking>();
+        if sneaking.remove(player_entity).is_some() {
+            false
+        } else {
+            sneaking.insert(player_entity, Sneaking{}).expect("Unable to insert sneaking");
+            true
+        }
+    };
+    ecs.write_resource::<GameEvents>().publish(GameEvent::SneakToggled{ entity: player_entity, sneaking: now_sneaking });
+    game_events::dispatch(ecs);
 }
 
 pub fn player_input(gamestate: &mut State, context: &mut Rltk) -> RunState {
@@ -100,11 +324,32 @@ pub fn player_input(gamestate: &mut State, context: &mut Rltk) -> RunState {
             VirtualKeyCode::P => get_item(&mut gamestate.ecs),
             VirtualKeyCode::I => return RunState::ShowInventory,
             VirtualKeyCode::D => return RunState::ShowDropItem,
+            VirtualKeyCode::A => {
+                if has_container(&gamestate.ecs) { return RunState::ShowPutAway; }
+                gamestate.ecs.fetch_mut::<GameLog>().entries.push("You have nothing to put things in.".to_string());
+                return RunState::AwaitingInput;
+            }
+
+            // Moving quietly
+            VirtualKeyCode::S => {
+                toggle_sneaking(&mut gamestate.ecs);
+                return RunState::AwaitingInput;
+            }
+
+            // Magic
+            VirtualKeyCode::Z => {
+                if knows_spells(&gamestate.ecs) { return RunState::ShowCastSpell; }
+                gamestate.ecs.fetch_mut::<GameLog>().entries.push("You don't know any spells.".to_string());
+                return RunState::AwaitingInput;
+            }
+
+            // Ranged attacks
+            VirtualKeyCode::F => return fire_weapon(&mut gamestate.ecs),
+            VirtualKeyCode::T => return RunState::ShowThrowItem,
 
-            //TODO fix impossible y axis with targeting upgrade to prevent play from hitting themselves
             //Waiting
             VirtualKeyCode::Key5 |
-            VirtualKeyCode::Numpad5 => try_move_player(0, 999, &mut gamestate.ecs),
+            VirtualKeyCode::Numpad5 => spend_player_energy(&gamestate.ecs, ActionType::Wait),
 
             _ => { return RunState::AwaitingInput }
         },
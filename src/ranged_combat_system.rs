@@ -0,0 +1,250 @@
+use specs::prelude::*;
+use rltk::RandomNumberGenerator;
+use super::{CombatStats, DamageType, WantsToFire, SufferDamage, DamageDice, Equipped, DefenseBonus,
+            RangedWeapon, Ammunition, Throwable, InBackpack, Position, Map, Attributes, Skills, Quantity, Durability, gamesystem,
+            durability_system::{WearQueue, USE_WEAR}, game_events::{GameEvents, GameEvent},
+            melee_combat_system::{resolve_attack, AttackResult, UNARMED}, stealth_system::{NoiseQueue, FIGHT_NOISE}};
+
+pub struct RangedCombatSystem {}
+
+impl<'a> System<'a> for RangedCombatSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        WriteExpect<'a, GameEvents>,
+                        Read<'a, LazyUpdate>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        ReadExpect<'a, Map>,
+                        WriteStorage<'a, WantsToFire>,
+                        ReadStorage<'a, CombatStats>,
+                        ReadStorage<'a, DamageDice>,
+                        WriteStorage<'a, SufferDamage>,
+                        WriteStorage<'a, Equipped>,
+                        ReadStorage<'a, DefenseBonus>,
+                        ReadStorage<'a, RangedWeapon>,
+                        ReadStorage<'a, Ammunition>,
+                        ReadStorage<'a, Throwable>,
+                        WriteStorage<'a, InBackpack>,
+                        WriteStorage<'a, Position>,
+                        ReadStorage<'a, Attributes>,
+                        ReadStorage<'a, Skills>,
+                        WriteExpect<'a, NoiseQueue>,
+                        WriteStorage<'a, Quantity>,
+                        ReadStorage<'a, Durability>,
+                        WriteExpect<'a, WearQueue>
+                      );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, mut events, lazy, mut rng, map, mut wants_fire, combat_stats, damage_dice, mut inflict_damage,
+            mut equipped, defense_bonuses, ranged_weapons, ammunition, throwables, mut backpack, mut positions,
+            attributes, skills, mut noise, mut quantities, durability, mut wear) = data;
+
+        for (entity, wants_fire, _stats) in (&entities, &wants_fire, &combat_stats).join() {
+            let projectile;
+
+            if let Some(weapon) = ranged_weapons.get(wants_fire.item) {
+                // Firing uses up one piece of matching ammunition from the backpack
+                let ammo = (&entities, &ammunition, &backpack).join()
+                    .find(|(_, ammo, pack)| pack.owner == entity && ammo.ammo == weapon.ammo)
+                    .map(|(ammo_entity, _, _)| ammo_entity);
+                match ammo {
+                    None => {
+                        events.publish(GameEvent::OutOfAmmo{ shooter: entity, weapon: wants_fire.item });
+                        continue;
+                    }
+                    Some(ammo) => {
+                        projectile = ammo;
+                        // The last one goes once everyone has heard about the shot
+                        match quantities.get_mut(ammo) {
+                            Some(stack) if stack.amount > 1 => stack.amount -= 1,
+                            _ => {
+                                backpack.remove(ammo);
+                                lazy.exec_mut(move |world| { world.delete_entity(ammo).expect("Delete failed"); });
+                            }
+                        }
+                        wear.wear(wants_fire.item, USE_WEAR);
+                    }
+                }
+            } else if throwables.get(wants_fire.item).is_some() {
+                // Thrown items land on the target tile, hit or miss
+                projectile = wants_fire.item;
+                backpack.remove(wants_fire.item);
+                equipped.remove(wants_fire.item);
+                positions.insert(wants_fire.item, Position{ x: wants_fire.target.x, y: wants_fire.target.y }).expect("Unable to insert position");
+            } else {
+                continue;
+            }
+
+            if let Some(pos) = positions.get(entity) {
+                noise.make(pos.x, pos.y, FIGHT_NOISE);
+            }
+
+            let index = map.xy_index(wants_fire.target.x, wants_fire.target.y);
+            let target = map.tile_content[index].iter().find(|mob| **mob != entity && combat_stats.get(**mob).is_some());
+            let target = match target {
+                None => {
+                    events.publish(GameEvent::LandedHarmlessly{ projectile });
+                    continue;
+                }
+                Some(target) => *target
+            };
+
+            let mut defensive_bonus = 0;
+            for (item_entity, equipped_by) in (&entities, &equipped).join() {
+                if equipped_by.owner == target && !durability.get(item_entity).is_some_and(|d| d.broken()) {
+                    if let Some(defense_bonus) = defense_bonuses.get(item_entity) {
+                        defensive_bonus += defense_bonus.defense;
+                    }
+                }
+            }
+
+            // A broken bow barely gets the shot away
+            let dice = match durability.get(wants_fire.item) {
+                Some(d) if d.broken() => &UNARMED,
+                _ => damage_dice.get(wants_fire.item).unwrap_or(&UNARMED)
+            };
+            let natural_roll = rng.roll_dice(1, 20);
+            let dice_total = rng.roll_dice(dice.n_dice, dice.die_type);
+            let attack_bonus = gamesystem::ranged_attack_bonus(&attributes.get(entity).cloned().unwrap_or_default(),
+                &skills.get(entity).cloned().unwrap_or_default());
+            let armour_class = gamesystem::armour_class(&attributes.get(target).cloned().unwrap_or_default(),
+                &skills.get(target).cloned().unwrap_or_default()) + defensive_bonus;
+
+            let result = resolve_attack(natural_roll, attack_bonus, armour_class, dice_total, dice.bonus);
+            events.publish(GameEvent::Shot{ shooter: entity, target, projectile, result });
+            if let AttackResult::Hit(damage) | AttackResult::Critical(damage) = result {
+                SufferDamage::new_damage(&mut inflict_damage, target, damage, DamageType::Physical, Some(entity));
+                wear.strike(target);
+            }
+        }
+
+        wants_fire.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{AmmoType, Item, Name};
+
+    /// A generator whose first d20, the attack roll, comes up `natural`.
+    fn rng_rolling(natural : i32) -> RandomNumberGenerator {
+        let seed = (0..).find(|seed| RandomNumberGenerator::seeded(*seed).roll_dice(1, 20) == natural).unwrap();
+        RandomNumberGenerator::seeded(seed)
+    }
+
+    /// An archer at 0,0 and a target at 5,0, both with average attributes.
+    fn range(natural : i32) -> (World, Entity, Entity) {
+        let mut ecs = World::new();
+        ecs.register::<Name>();
+        ecs.register::<CombatStats>();
+        ecs.register::<DamageDice>();
+        ecs.register::<SufferDamage>();
+        ecs.register::<WantsToFire>();
+        ecs.register::<Equipped>();
+        ecs.register::<DefenseBonus>();
+        ecs.register::<RangedWeapon>();
+        ecs.register::<Ammunition>();
+        ecs.register::<Throwable>();
+        ecs.register::<InBackpack>();
+        ecs.register::<Position>();
+        ecs.register::<Attributes>();
+        ecs.register::<Skills>();
+        ecs.register::<Quantity>();
+        ecs.register::<Durability>();
+        ecs.register::<Item>();
+        ecs.insert(GameEvents::default());
+        ecs.insert(rng_rolling(natural));
+        ecs.insert(NoiseQueue::new());
+        ecs.insert(WearQueue::new());
+
+        let archer = ecs.create_entity()
+            .with(Name{ name: "Archer".to_string() })
+            .with(CombatStats{ max_hp: 10, hp: 10 })
+            .with(Position{ x: 0, y: 0 })
+            .build();
+        let target = ecs.create_entity()
+            .with(Name{ name: "Target".to_string() })
+            .with(CombatStats{ max_hp: 10, hp: 10 })
+            .with(Position{ x: 5, y: 0 })
+            .build();
+        let mut map = Map{ width: 10, height: 1, tile_content: vec![Vec::new(); 10], ..Default::default() };
+        map.tile_content[5].push(target);
+        ecs.insert(map);
+        (ecs, archer, target)
+    }
+
+    fn carried<'a>(ecs : &'a mut World, owner : Entity, name : &str) -> EntityBuilder<'a> {
+        ecs.create_entity()
+            .with(Name{ name: name.to_string() })
+            .with(Item{})
+            .with(InBackpack{ owner })
+    }
+
+    fn fire(ecs : &mut World, shooter : Entity, item : Entity, x : i32) {
+        ecs.write_storage::<WantsToFire>().insert(shooter, WantsToFire{ item, target: rltk::Point::new(x, 0) }).unwrap();
+        RangedCombatSystem{}.run_now(ecs);
+        ecs.maintain();
+    }
+
+    fn bow(ecs : &mut World, owner : Entity) -> Entity {
+        carried(ecs, owner, "Bow")
+            .with(RangedWeapon{ range: 8, ammo: AmmoType::Arrow })
+            .with(DamageDice{ n_dice: 1, die_type: 6, bonus: 0 })
+            .build()
+    }
+
+    fn hurt(ecs : &World, target : Entity) -> bool {
+        ecs.read_storage::<SufferDamage>().get(target).is_some()
+    }
+
+    #[test]
+    fn firing_uses_one_piece_of_ammunition() {
+        let (mut ecs, archer, _) = range(10);
+        let bow = bow(&mut ecs, archer);
+        let arrows = carried(&mut ecs, archer, "Arrow").with(Ammunition{ ammo: AmmoType::Arrow }).with(Quantity{ amount: 2 }).build();
+
+        fire(&mut ecs, archer, bow, 5);
+        assert_eq!(ecs.read_storage::<Quantity>().get(arrows).unwrap().amount, 1);
+        fire(&mut ecs, archer, bow, 5);
+        assert!(!ecs.entities().is_alive(arrows));
+    }
+
+    #[test]
+    fn nothing_is_fired_without_matching_ammunition() {
+        let (mut ecs, archer, target) = range(20);
+        let bow = bow(&mut ecs, archer);
+        let bolts = carried(&mut ecs, archer, "Bolt").with(Ammunition{ ammo: AmmoType::Bolt }).with(Quantity{ amount: 3 }).build();
+
+        fire(&mut ecs, archer, bow, 5);
+        assert!(!hurt(&ecs, target));
+        assert_eq!(ecs.read_storage::<Quantity>().get(bolts).unwrap().amount, 3);
+        assert_eq!(ecs.fetch::<GameEvents>().events, vec![GameEvent::OutOfAmmo{ shooter: archer, weapon: bow }]);
+    }
+
+    #[test]
+    fn shots_hit_or_miss_against_armour_class() {
+        let (mut ecs, archer, target) = range(10);
+        let javelin = carried(&mut ecs, archer, "Javelin").with(Throwable{ range: 7 }).build();
+        fire(&mut ecs, archer, javelin, 5);
+        assert!(hurt(&ecs, target));
+
+        let (mut ecs, archer, target) = range(9);
+        let javelin = carried(&mut ecs, archer, "Javelin").with(Throwable{ range: 7 }).build();
+        fire(&mut ecs, archer, javelin, 5);
+        assert!(!hurt(&ecs, target));
+        let shot = GameEvent::Shot{ shooter: archer, target, projectile: javelin, result: AttackResult::Miss };
+        assert_eq!(ecs.fetch::<GameEvents>().events, vec![shot]);
+    }
+
+    #[test]
+    fn thrown_items_land_on_the_target_tile() {
+        let (mut ecs, archer, _) = range(1);
+        let javelin = carried(&mut ecs, archer, "Javelin").with(Throwable{ range: 7 }).build();
+
+        fire(&mut ecs, archer, javelin, 3);
+        assert!(ecs.read_storage::<InBackpack>().get(javelin).is_none());
+        let positions = ecs.read_storage::<Position>();
+        let landed = positions.get(javelin).unwrap();
+        assert_eq!((landed.x, landed.y), (3, 0));
+    }
+}
@@ -0,0 +1,112 @@
+use specs::prelude::*;
+use super::{CombatStats, Mana, NoRegeneration, Monster, Position, Viewshed, HungerClock, HungerState,
+            initiative_system::GameClock};
+
+/// Everyone who can heals one hit point every this many turns.
+pub const REGEN_TURNS : i32 = 10;
+/// Mana comes back faster, and whatever is going on nearby.
+pub const MANA_REGEN_TURNS : i32 = 4;
+
+/// Slow natural healing and mana recovery. The player only recovers while no hostile is in sight and they
+/// aren't starving, so resting is something to be done somewhere safe.
+pub struct RegenerationSystem {}
+
+impl<'a> System<'a> for RegenerationSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, GameClock>,
+                        ReadExpect<'a, Entity>,
+                        WriteStorage<'a, CombatStats>,
+                        ReadStorage<'a, NoRegeneration>,
+                        ReadStorage<'a, Monster>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, Viewshed>,
+                        ReadStorage<'a, HungerClock>,
+                        WriteStorage<'a, Mana> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, clock, player_entity, mut combat_stats, no_regen, monsters, positions, viewsheds, hunger, mut mana) = data;
+
+        if !clock.ticked { return; }
+
+        if clock.turns % MANA_REGEN_TURNS == 0 {
+            for pool in (&mut mana).join() {
+                pool.current = i32::min(pool.max, pool.current + 1);
+            }
+        }
+
+        if clock.turns % REGEN_TURNS != 0 { return; }
+
+        let player_safe = viewsheds.get(*player_entity).is_none_or(|viewshed| {
+            !(&monsters, &positions).join()
+                .any(|(_, pos)| viewshed.visible_tiles.contains(&rltk::Point::new(pos.x, pos.y)))
+        }) && hunger.get(*player_entity).is_none_or(|h| h.state != HungerState::Starving);
+
+        for (entity, stats, _) in (&entities, &mut combat_stats, !&no_regen).join() {
+            if stats.hp < 1 || (entity == *player_entity && !player_safe) { continue; }
+            stats.hp = i32::min(stats.max_hp, stats.hp + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A wounded player who can see the tile at 1,1, on turn `turns`.
+    fn world_on_turn(turns : i32) -> (World, Entity) {
+        let mut ecs = World::new();
+        ecs.register::<CombatStats>();
+        ecs.register::<NoRegeneration>();
+        ecs.register::<Monster>();
+        ecs.register::<Position>();
+        ecs.register::<Viewshed>();
+        ecs.register::<HungerClock>();
+        ecs.register::<Mana>();
+        ecs.insert(GameClock{ turns, ticked: true });
+        let player = ecs.create_entity()
+            .with(CombatStats{ max_hp: 10, hp: 5 })
+            .with(Viewshed{ visible_tiles: vec![rltk::Point::new(1, 1)], range: 8, dirty: false })
+            .build();
+        ecs.insert(player);
+        (ecs, player)
+    }
+
+    fn hp(ecs : &World, entity : Entity) -> i32 {
+        ecs.read_storage::<CombatStats>().get(entity).unwrap().hp
+    }
+
+    #[test]
+    fn heals_one_point_every_few_turns() {
+        let (mut ecs, player) = world_on_turn(REGEN_TURNS - 1);
+        RegenerationSystem{}.run_now(&ecs);
+        assert_eq!(hp(&ecs, player), 5);
+
+        ecs.insert(GameClock{ turns: REGEN_TURNS, ticked: true });
+        RegenerationSystem{}.run_now(&ecs);
+        assert_eq!(hp(&ecs, player), 6);
+    }
+
+    #[test]
+    fn some_creatures_never_heal() {
+        let (mut ecs, _) = world_on_turn(REGEN_TURNS);
+        let zombie = ecs.create_entity().with(CombatStats{ max_hp: 10, hp: 5 }).with(NoRegeneration{}).build();
+        let corpse = ecs.create_entity().with(CombatStats{ max_hp: 10, hp: 0 }).build();
+        RegenerationSystem{}.run_now(&ecs);
+        assert_eq!(hp(&ecs, zombie), 5);
+        assert_eq!(hp(&ecs, corpse), 0);
+    }
+
+    #[test]
+    fn the_player_only_heals_when_safe() {
+        let (mut ecs, player) = world_on_turn(REGEN_TURNS);
+        let orc = ecs.create_entity().with(Monster{}).with(Position{ x: 1, y: 1 }).build();
+        RegenerationSystem{}.run_now(&ecs);
+        assert_eq!(hp(&ecs, player), 5);
+
+        ecs.delete_entity(orc).unwrap();
+        ecs.write_storage::<HungerClock>().insert(player, HungerClock{ state: HungerState::Starving, duration: 0 }).unwrap();
+        RegenerationSystem{}.run_now(&ecs);
+        assert_eq!(hp(&ecs, player), 5);
+    }
+}
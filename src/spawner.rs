@@ -1,10 +1,22 @@
 use rltk::{ RGB, RandomNumberGenerator };
 use specs::prelude::*;
 use super::{CombatStats, Player, Renderable, Name, Position, Viewshed, Monster, BlocksTile, Rect, Item,
-            Consumable, Ranged, ProvidesHealing, map::MAPWIDTH, InflictsDamage, AreaOfEffect, Confusion};
+            Consumable, Ranged, ProvidesHealing, map::MAPWIDTH, InflictsDamage, AreaOfEffect, DamageDice,
+            Equippable, EquipmentSlot, MeleePowerBonus, DefenseBonus, RangedWeapon, Ammunition, AmmoType, Throwable,
+            DamageType, Resistances, InflictsStatus, StatusKind, Initiative, XpValue, Experience,
+            Attributes, Skills, ProvidesFood, HungerClock, HungerState, Knockback, Unaware, Morale, NoRegeneration,
+            Mana, Spell, KnownSpells, TeachesSpell, Follower, Quantity, Weight, Encumbrance, ObfuscatedName,
+            IdentifiesItems, Cursed, RemovesCurse, Charges, Recharges, Durability, Metal, Corrodes, RepairsItems,
+            Container, Scroll, gamesystem, identification::{self, ItemIdentities, MagicItemKind},
+            initiative_system::{NORMAL_SPEED, ACTION_THRESHOLD}, hunger_system, morale_system};
 
 /// Spawns the player and returns his/her entity object.
 pub fn player(ecs : &mut World, player_x : i32, player_y : i32) -> Entity {
+    let attributes = Attributes{ might: 12, fitness: 13, quickness: 12, intelligence: 10 };
+    let skills = Skills{ melee: 4, defense: 4, magic: 0, stealth: 2 };
+    let hp = gamesystem::max_hp(&attributes, 1);
+    let mana = gamesystem::max_mana(&attributes, &skills, 1);
+    let capacity = gamesystem::carry_capacity(&attributes);
     ecs
         .create_entity()
         .with(Position { x: player_x, y: player_y })
@@ -17,7 +29,40 @@ pub fn player(ecs : &mut World, player_x : i32, player_y : i32) -> Entity {
         .with(Player{})
         .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
         .with(Name{name: "Player".to_string() })
-        .with(CombatStats{ max_hp: 30, hp: 30, defense: 5, power: 5 })
+        .with(CombatStats{ max_hp: hp, hp })
+        .with(attributes)
+        .with(skills)
+        .with(Mana{ current: mana, max: mana })
+        .with(KnownSpells::default())
+        .with(DamageDice{ n_dice: 1, die_type: 6, bonus: 1 })
+        .with(Initiative{ speed: NORMAL_SPEED, energy: ACTION_THRESHOLD })
+        .with(Experience{ level: 1, xp: 0 })
+        .with(HungerClock{ state: HungerState::WellFed, duration: hunger_system::WELL_FED_TURNS })
+        .with(Encumbrance{ load: 0.0, capacity })
+        .build()
+}
+
+/// Spawns the player's faithful dog, which follows `leader` around.
+pub fn dog(ecs : &mut World, x : i32, y : i32, leader : Entity) -> Entity {
+    let attributes = Attributes{ might: 10, fitness: 8, quickness: 14, intelligence: 6 };
+    let hp = gamesystem::max_hp(&attributes, 1);
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('d'),
+            foreground: RGB::named(rltk::YELLOWGREEN),
+            background: RGB::named(rltk::BLACK),
+            render_order: 1
+        })
+        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
+        .with(Name{ name : "Dog".to_string() })
+        .with(BlocksTile{})
+        .with(CombatStats{ max_hp: hp, hp })
+        .with(attributes)
+        .with(Skills{ melee: 3, defense: 2, magic: 0, stealth: 2 })
+        .with(DamageDice{ n_dice: 1, die_type: 6, bonus: 0 })
+        .with(Initiative{ speed: NORMAL_SPEED, energy: 0 })
+        .with(Follower{ leader })
         .build()
 }
 
@@ -81,13 +126,17 @@ fn random_monster(ecs: &mut World, x: i32, y: i32) {
     let roll :i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 2);
+        roll = rng.roll_dice(1, 7);
     }
     match roll {
         1 => { orc (ecs, x, y) }
         2 => { goblin (ecs, x, y) }
-        3 => { hobgoblin(ecs, x, y)}
-        4 => { rust_monster(ecs, x, y)}
+        3 => { ice_wraith(ecs, x, y) }
+        4 => { cave_spider(ecs, x, y) }
+        5 => { bat(ecs, x, y) }
+        6 => { zombie(ecs, x, y) }
+        7 => { rust_monster(ecs, x, y)}
+        8 => { hobgoblin(ecs, x, y)}
         _ => {ferris(ecs, x, y)}
     }
 }
@@ -96,12 +145,38 @@ fn random_item(ecs: &mut World, x: i32, y: i32) {
     let roll :i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 4);
+        roll = rng.roll_dice(1, 30);
     }
     match roll {
         1 => { health_potion(ecs, x, y) }
         2 => { fireball_scroll(ecs, x, y) }
         3 => { confusion_scroll(ecs, x, y) }
+        4 => { dagger(ecs, x, y) }
+        5 => { longsword(ecs, x, y) }
+        6 => { shield(ecs, x, y) }
+        7 => { leather_armour(ecs, x, y) }
+        8 => { helmet(ecs, x, y) }
+        9 => { javelin(ecs, x, y) }
+        10 => { bow(ecs, x, y) }
+        11 => { crossbow(ecs, x, y) }
+        12 => { arrows(ecs, x, y) }
+        13 => { bolts(ecs, x, y) }
+        14 => { haste_potion(ecs, x, y) }
+        15 => { sleep_scroll(ecs, x, y) }
+        16 => { rations(ecs, x, y) }
+        17 => { repulsion_scroll(ecs, x, y) }
+        18 => { war_hammer(ecs, x, y) }
+        19 => { regeneration_potion(ecs, x, y) }
+        20 => { spellbook(ecs, x, y) }
+        21 => { identify_scroll(ecs, x, y) }
+        22 => { remove_curse_scroll(ecs, x, y) }
+        23 => { magic_missile_wand(ecs, x, y) }
+        24 => { fireball_wand(ecs, x, y) }
+        25 => { confusion_wand(ecs, x, y) }
+        26 => { recharging_scroll(ecs, x, y) }
+        27 => { repair_kit(ecs, x, y) }
+        28 => { bag(ecs, x, y) }
+        29 => { scroll_case(ecs, x, y) }
         _ => { magic_missile_scroll(ecs, x, y) }
     }
 }
@@ -109,10 +184,76 @@ fn random_item(ecs: &mut World, x: i32, y: i32) {
 fn orc (ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('o'), "Orc"); }
 fn goblin (ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('g'), "Goblin"); }
 fn hobgoblin (ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('h'), "Hobgoblin"); }
-fn rust_monster (ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('r'), "Rust Monster" );}
+fn rust_monster (ecs: &mut World, x: i32, y: i32) {
+    let rust_monster = monster(ecs, x, y, rltk::to_cp437('r'), "Rust Monster");
+    ecs.write_storage::<Corrodes>().insert(rust_monster, Corrodes{}).expect("Unable to insert corrosion");
+}
 fn ferris (ecs: &mut World, x: i32, y:i32) {monster(ecs, x, y, rltk::to_cp437('F'), "Ferris The Rustacean");}
 
-fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, glyph : rltk::FontCharType, name : S) {
+fn ice_wraith (ecs: &mut World, x: i32, y: i32) {
+    let wraith = monster(ecs, x, y, rltk::to_cp437('W'), "Ice Wraith");
+    ecs.write_storage::<Resistances>()
+        .insert(wraith, Resistances{ resist: Vec::new(), immune: vec![DamageType::Cold], weak: vec![DamageType::Fire] })
+        .expect("Unable to insert resistances");
+}
+
+fn cave_spider (ecs: &mut World, x: i32, y: i32) {
+    let spider = monster(ecs, x, y, rltk::to_cp437('s'), "Cave Spider");
+    ecs.write_storage::<InflictsStatus>()
+        .insert(spider, InflictsStatus{ kind: StatusKind::Poison, turns: 4, magnitude: 1 })
+        .expect("Unable to insert venom");
+}
+
+/// Bats are frail but act twice for every move the player makes.
+fn bat (ecs: &mut World, x: i32, y: i32) {
+    let bat = monster(ecs, x, y, rltk::to_cp437('b'), "Bat");
+    override_stats(ecs, bat,
+        Attributes{ might: 8, fitness: 3, quickness: 14, intelligence: 4 },
+        Skills{ melee: 4, defense: 0, magic: 0, stealth: 0 });
+    ecs.write_storage::<DamageDice>()
+        .insert(bat, DamageDice{ n_dice: 1, die_type: 4, bonus: 0 })
+        .expect("Unable to insert damage");
+    ecs.write_storage::<Initiative>()
+        .insert(bat, Initiative{ speed: NORMAL_SPEED * 2, energy: 0 })
+        .expect("Unable to insert initiative");
+    ecs.write_storage::<XpValue>()
+        .insert(bat, XpValue{ xp: 8 })
+        .expect("Unable to insert xp value");
+}
+
+/// Zombies are tough and hit hard, but only act every other move. They are too mindless
+/// to ever flee, and being dead already they never heal.
+fn zombie (ecs: &mut World, x: i32, y: i32) {
+    let zombie = monster(ecs, x, y, rltk::to_cp437('z'), "Zombie");
+    ecs.write_storage::<Morale>().remove(zombie);
+    ecs.write_storage::<NoRegeneration>().insert(zombie, NoRegeneration{}).expect("Unable to insert marker");
+    override_stats(ecs, zombie,
+        Attributes{ might: 12, fitness: 10, quickness: 8, intelligence: 3 },
+        Skills{ melee: 3, defense: 1, magic: 0, stealth: 0 });
+    ecs.write_storage::<DamageDice>()
+        .insert(zombie, DamageDice{ n_dice: 1, die_type: 8, bonus: 0 })
+        .expect("Unable to insert damage");
+    ecs.write_storage::<Initiative>()
+        .insert(zombie, Initiative{ speed: NORMAL_SPEED / 2, energy: 0 })
+        .expect("Unable to insert initiative");
+    ecs.write_storage::<XpValue>()
+        .insert(zombie, XpValue{ xp: 20 })
+        .expect("Unable to insert xp value");
+}
+
+/// Replaces a monster's attributes and skills, re-deriving its hit points from them.
+fn override_stats(ecs: &mut World, entity: Entity, attributes: Attributes, skills: Skills) {
+    let hp = gamesystem::max_hp(&attributes, 1);
+    ecs.write_storage::<CombatStats>().insert(entity, CombatStats{ max_hp: hp, hp }).expect("Unable to insert stats");
+    ecs.write_storage::<Attributes>().insert(entity, attributes).expect("Unable to insert attributes");
+    ecs.write_storage::<Skills>().insert(entity, skills).expect("Unable to insert skills");
+}
+
+/// Monsters start out unaware of the player, and one in three is asleep.
+fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, glyph : rltk::FontCharType, name : S) -> Entity {
+    let asleep = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 3) == 1;
+    let attributes = Attributes{ might: 10, fitness: 7, quickness: 10, intelligence: 8 };
+    let hp = gamesystem::max_hp(&attributes, 1);
     ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
@@ -125,74 +266,645 @@ fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, glyph : rltk::FontChar
         .with(Monster{})
         .with(Name{ name : name.to_string() })
         .with(BlocksTile{})
-        .with(CombatStats{ max_hp: 16, hp: 16, defense: 1, power: 4 })
-        .build();
+        .with(CombatStats{ max_hp: hp, hp })
+        .with(attributes)
+        .with(Skills{ melee: 4, defense: 1, magic: 0, stealth: 0 })
+        .with(DamageDice{ n_dice: 1, die_type: 4, bonus: 0 })
+        .with(Initiative{ speed: NORMAL_SPEED, energy: 0 })
+        .with(XpValue{ xp: 15 })
+        .with(Unaware{ asleep })
+        .with(Morale{ value: morale_system::BASE_MORALE, max: morale_system::BASE_MORALE, fleeing: false })
+        .build()
+}
+
+/// One in this many pieces of equipment and scrolls comes cursed.
+const CURSE_CHANCE : i32 = 8;
+
+fn curse_sometimes(ecs: &mut World, item : Entity) {
+    let cursed = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, CURSE_CHANCE) == 1;
+    if cursed {
+        ecs.write_storage::<Cursed>().insert(item, Cursed{}).expect("Unable to insert curse");
+    }
+}
+
+/// What a magic item looks like until the player works out what it is.
+fn disguise(ecs: &mut World, true_name : &str, kind : MagicItemKind) -> ObfuscatedName {
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let name = ecs.write_resource::<ItemIdentities>().disguise(&mut rng, true_name, kind);
+    ObfuscatedName{ name }
 }
 
 fn health_potion(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Health Potion", MagicItemKind::Potion);
     ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
             glyph: rltk::to_cp437('¡'),
-            foreground: RGB::named(rltk::MAGENTA),
+            foreground: identification::disguise_colour(MagicItemKind::Potion, &disguise.name),
             background: RGB::named(rltk::BLACK),
             render_order: 2
         })
         .with(Name{ name : "Health Potion".to_string() })
+        .with(disguise)
         .with(Item{})
+        .with(Weight{ lbs: 0.5 })
         .with(Consumable{})
+        .with(Quantity{ amount: 1 })
         .with(ProvidesHealing{ heal_amount: 8 })
         .build();
 }
 
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
+fn rations(ecs: &mut World, x: i32, y: i32) {
     ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('%'),
+            foreground: RGB::named(rltk::GREEN),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Rations".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 1.0 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(ProvidesFood{})
+        .build();
+}
+
+fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Magic Missile Scroll", MagicItemKind::Scroll);
+    let item = ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
             glyph: rltk::to_cp437(')'),
-            foreground: RGB::named(rltk::CYAN),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
             background: RGB::named(rltk::BLACK),
             render_order: 2
         })
         .with(Name{ name : "Magic Missile Scroll".to_string() })
+        .with(disguise)
+        .with(Scroll{})
         .with(Item{})
+        .with(Weight{ lbs: 0.5 })
         .with(Consumable{})
+        .with(Quantity{ amount: 1 })
         .with(Ranged{ range: 6 })
-        .with(InflictsDamage{ damage: 20 })
+        .with(InflictsDamage{ damage: 20, damage_type: DamageType::Magic })
         .build();
+    curse_sometimes(ecs, item);
 }
 
 fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
+    let disguise = disguise(ecs, "Fireball Scroll", MagicItemKind::Scroll);
+    let item = ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
             glyph: rltk::to_cp437(')'),
-            foreground: RGB::named(rltk::ORANGE),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
             background: RGB::named(rltk::BLACK),
             render_order: 2
         })
         .with(Name{ name : "Fireball Scroll".to_string() })
+        .with(disguise)
+        .with(Scroll{})
         .with(Item{})
+        .with(Weight{ lbs: 0.5 })
         .with(Consumable{})
+        .with(Quantity{ amount: 1 })
         .with(Ranged{ range: 6 })
-        .with(InflictsDamage{ damage: 20 })
+        .with(InflictsDamage{ damage: 20, damage_type: DamageType::Fire })
+        .with(InflictsStatus{ kind: StatusKind::Burning, turns: 3, magnitude: 2 })
         .with(AreaOfEffect{ radius: 3 })
         .build();
+    curse_sometimes(ecs, item);
 }
 
 fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
-    ecs.create_entity()
+    let disguise = disguise(ecs, "Confusion Scroll", MagicItemKind::Scroll);
+    let item = ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
             glyph: rltk::to_cp437(')'),
-            foreground: RGB::named(rltk::PINK),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
             background: RGB::named(rltk::BLACK),
             render_order: 2
         })
         .with(Name{ name : "Confusion Scroll".to_string() })
+        .with(disguise)
+        .with(Scroll{})
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(Ranged{ range: 6 })
+        .with(InflictsStatus{ kind: StatusKind::Confusion, turns: 4, magnitude: 0 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn dagger(ecs: &mut World, x: i32, y: i32) {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('/'),
+            foreground: RGB::named(rltk::CYAN),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Dagger".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 1.0 })
+        .with(Durability{ current: 30, max: 30 })
+        .with(Metal{})
+        .with(Equippable{ slot: EquipmentSlot::Melee })
+        .with(Throwable{ range: 5 })
+        .with(MeleePowerBonus{ power: 2 })
+        .with(DamageDice{ n_dice: 1, die_type: 4, bonus: 2 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn longsword(ecs: &mut World, x: i32, y: i32) {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('/'),
+            foreground: RGB::named(rltk::YELLOW),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Longsword".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 3.0 })
+        .with(Durability{ current: 40, max: 40 })
+        .with(Metal{})
+        .with(Equippable{ slot: EquipmentSlot::Melee })
+        .with(MeleePowerBonus{ power: 1 })
+        .with(DamageDice{ n_dice: 1, die_type: 8, bonus: 3 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn war_hammer(ecs: &mut World, x: i32, y: i32) {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('/'),
+            foreground: RGB::named(rltk::ORANGE),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "War Hammer".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 10.0 })
+        .with(Durability{ current: 50, max: 50 })
+        .with(Metal{})
+        .with(Equippable{ slot: EquipmentSlot::Melee })
+        .with(DamageDice{ n_dice: 1, die_type: 8, bonus: 0 })
+        .with(Knockback{ distance: 2 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn shield(ecs: &mut World, x: i32, y: i32) {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('('),
+            foreground: RGB::named(rltk::CYAN),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Shield".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 8.0 })
+        .with(Durability{ current: 40, max: 40 })
+        .with(Metal{})
+        .with(Equippable{ slot: EquipmentSlot::Shield })
+        .with(DefenseBonus{ defense: 2 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn leather_armour(ecs: &mut World, x: i32, y: i32) {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('['),
+            foreground: RGB::named(rltk::BROWN1),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Leather Armour".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 10.0 })
+        .with(Durability{ current: 30, max: 30 })
+        .with(Equippable{ slot: EquipmentSlot::Armour })
+        .with(DefenseBonus{ defense: 2 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn helmet(ecs: &mut World, x: i32, y: i32) {
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('^'),
+            foreground: RGB::named(rltk::GREY),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Helmet".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 2.0 })
+        .with(Durability{ current: 30, max: 30 })
+        .with(Metal{})
+        .with(Equippable{ slot: EquipmentSlot::Head })
+        .with(DefenseBonus{ defense: 1 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn javelin(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('|'),
+            foreground: RGB::named(rltk::BROWN1),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Javelin".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 2.0 })
+        .with(Throwable{ range: 7 })
+        .with(DamageDice{ n_dice: 1, die_type: 6, bonus: 2 })
+        .build();
+}
+
+fn bow(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('}'),
+            foreground: RGB::named(rltk::BROWN1),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Shortbow".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 2.0 })
+        .with(Durability{ current: 30, max: 30 })
+        .with(Equippable{ slot: EquipmentSlot::Ranged })
+        .with(RangedWeapon{ range: 8, ammo: AmmoType::Arrow })
+        .with(DamageDice{ n_dice: 1, die_type: 6, bonus: 0 })
+        .build();
+}
+
+fn crossbow(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('}'),
+            foreground: RGB::named(rltk::GREY),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Crossbow".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 6.0 })
+        .with(Durability{ current: 40, max: 40 })
+        .with(Equippable{ slot: EquipmentSlot::Ranged })
+        .with(RangedWeapon{ range: 6, ammo: AmmoType::Bolt })
+        .with(DamageDice{ n_dice: 1, die_type: 10, bonus: 0 })
+        .build();
+}
+
+fn arrows(ecs: &mut World, x: i32, y: i32) {
+    ammunition(ecs, x, y, "Arrow", AmmoType::Arrow, 5);
+}
+
+fn bolts(ecs: &mut World, x: i32, y: i32) {
+    ammunition(ecs, x, y, "Bolt", AmmoType::Bolt, 4);
+}
+
+fn ammunition<S : ToString>(ecs: &mut World, x: i32, y: i32, name : S, ammo : AmmoType, amount : i32) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('-'),
+            foreground: RGB::named(rltk::BROWN1),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 0.1 })
+        .with(Ammunition{ ammo })
+        .with(Quantity{ amount })
+        .build();
+}
+
+fn repulsion_scroll(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Scroll of Repulsion", MagicItemKind::Scroll);
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(')'),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Scroll of Repulsion".to_string() })
+        .with(disguise)
+        .with(Scroll{})
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(AreaOfEffect{ radius: 2 })
+        .with(Knockback{ distance: 4 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn haste_potion(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Potion of Haste", MagicItemKind::Potion);
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('¡'),
+            foreground: identification::disguise_colour(MagicItemKind::Potion, &disguise.name),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Potion of Haste".to_string() })
+        .with(disguise)
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(InflictsStatus{ kind: StatusKind::Haste, turns: 12, magnitude: 0 })
+        .build();
+}
+
+fn regeneration_potion(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Potion of Regeneration", MagicItemKind::Potion);
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('¡'),
+            foreground: identification::disguise_colour(MagicItemKind::Potion, &disguise.name),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Potion of Regeneration".to_string() })
+        .with(disguise)
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(InflictsStatus{ kind: StatusKind::Regeneration, turns: 20, magnitude: 1 })
+        .build();
+}
+
+fn sleep_scroll(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Sleep Scroll", MagicItemKind::Scroll);
+    let item = ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(')'),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Sleep Scroll".to_string() })
+        .with(disguise)
+        .with(Scroll{})
         .with(Item{})
+        .with(Weight{ lbs: 0.5 })
         .with(Consumable{})
+        .with(Quantity{ amount: 1 })
         .with(Ranged{ range: 6 })
-        .with(Confusion{ turns: 4 })
+        .with(AreaOfEffect{ radius: 2 })
+        .with(InflictsStatus{ kind: StatusKind::Sleep, turns: 8, magnitude: 0 })
+        .build();
+    curse_sometimes(ecs, item);
+}
+
+fn identify_scroll(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Scroll of Identify", MagicItemKind::Scroll);
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(')'),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Scroll of Identify".to_string() })
+        .with(disguise)
+        .with(Scroll{})
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(IdentifiesItems{})
+        .build();
+}
+
+fn remove_curse_scroll(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Scroll of Remove Curse", MagicItemKind::Scroll);
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(')'),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Scroll of Remove Curse".to_string() })
+        .with(disguise)
+        .with(Scroll{})
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(RemovesCurse{})
+        .build();
+}
+
+fn recharging_scroll(ecs: &mut World, x: i32, y: i32) {
+    let disguise = disguise(ecs, "Scroll of Recharging", MagicItemKind::Scroll);
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437(')'),
+            foreground: identification::disguise_colour(MagicItemKind::Scroll, &disguise.name),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Scroll of Recharging".to_string() })
+        .with(disguise)
+        .with(Scroll{})
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(Recharges{})
         .build();
-}
\ No newline at end of file
+}
+
+fn repair_kit(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('&'),
+            foreground: RGB::named(rltk::LIGHT_GRAY),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Repair Kit".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 2.0 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(RepairsItems{})
+        .build();
+}
+
+fn bag(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('('),
+            foreground: RGB::named(rltk::BROWN1),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Bag".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 1.0 })
+        .with(Container{ max_weight: 15.0, scrolls_only: false })
+        .build();
+}
+
+fn scroll_case(ecs: &mut World, x: i32, y: i32) {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('('),
+            foreground: RGB::named(rltk::WHEAT),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : "Scroll Case".to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 0.5 })
+        .with(Container{ max_weight: 5.0, scrolls_only: true })
+        .build();
+}
+
+/// Starts building a wand with a few charges in it. Wands are used like scrolls, but
+/// aren't used up.
+fn wand<S : ToString>(ecs: &mut World, x: i32, y: i32, name : S, colour : RGB) -> EntityBuilder<'_> {
+    let charges = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 3) + 2;
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('-'),
+            foreground: colour,
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : name.to_string() })
+        .with(Item{})
+        .with(Weight{ lbs: 1.0 })
+        .with(Charges{ current: charges, max: charges })
+}
+
+fn magic_missile_wand(ecs: &mut World, x: i32, y: i32) {
+    wand(ecs, x, y, "Wand of Magic Missile", RGB::named(rltk::CYAN))
+        .with(Ranged{ range: 6 })
+        .with(InflictsDamage{ damage: 20, damage_type: DamageType::Magic })
+        .build();
+}
+
+fn fireball_wand(ecs: &mut World, x: i32, y: i32) {
+    wand(ecs, x, y, "Wand of Fireball", RGB::named(rltk::ORANGE))
+        .with(Ranged{ range: 6 })
+        .with(InflictsDamage{ damage: 20, damage_type: DamageType::Fire })
+        .with(InflictsStatus{ kind: StatusKind::Burning, turns: 3, magnitude: 2 })
+        .with(AreaOfEffect{ radius: 3 })
+        .build();
+}
+
+fn confusion_wand(ecs: &mut World, x: i32, y: i32) {
+    wand(ecs, x, y, "Wand of Confusion", RGB::named(rltk::PINK))
+        .with(Ranged{ range: 6 })
+        .with(InflictsStatus{ kind: StatusKind::Confusion, turns: 4, magnitude: 0 })
+        .build();
+}
+
+/// A spellbook teaching one of the spells, picked at random.
+fn spellbook(ecs: &mut World, x: i32, y: i32) {
+    let roll = ecs.write_resource::<RandomNumberGenerator>().roll_dice(1, 4);
+    let spell = match roll {
+        1 => magic_missile_spell(ecs),
+        2 => fireball_spell(ecs),
+        3 => confusion_spell(ecs),
+        _ => cure_wounds_spell(ecs)
+    };
+    let title = format!("Spellbook of {}", ecs.read_storage::<Name>().get(spell).unwrap().name);
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph: rltk::to_cp437('+'),
+            foreground: RGB::named(rltk::PURPLE),
+            background: RGB::named(rltk::BLACK),
+            render_order: 2
+        })
+        .with(Name{ name : title })
+        .with(Item{})
+        .with(Weight{ lbs: 2.0 })
+        .with(Consumable{})
+        .with(Quantity{ amount: 1 })
+        .with(TeachesSpell{ spell })
+        .build();
+}
+
+/// Starts building a spell. It has no position, so it only exists to be learned and cast.
+fn spell<S : ToString>(ecs: &mut World, name : S, mana_cost : i32) -> EntityBuilder<'_> {
+    ecs.create_entity()
+        .with(Name{ name : name.to_string() })
+        .with(Spell{ mana_cost })
+}
+
+fn magic_missile_spell(ecs: &mut World) -> Entity {
+    spell(ecs, "Magic Missile", 2)
+        .with(Ranged{ range: 6 })
+        .with(InflictsDamage{ damage: 8, damage_type: DamageType::Magic })
+        .build()
+}
+
+fn fireball_spell(ecs: &mut World) -> Entity {
+    spell(ecs, "Fireball", 5)
+        .with(Ranged{ range: 6 })
+        .with(InflictsDamage{ damage: 12, damage_type: DamageType::Fire })
+        .with(AreaOfEffect{ radius: 2 })
+        .build()
+}
+
+fn confusion_spell(ecs: &mut World) -> Entity {
+    spell(ecs, "Confusion", 3)
+        .with(Ranged{ range: 6 })
+        .with(InflictsStatus{ kind: StatusKind::Confusion, turns: 4, magnitude: 0 })
+        .build()
+}
+
+fn cure_wounds_spell(ecs: &mut World) -> Entity {
+    spell(ecs, "Cure Wounds", 4)
+        .with(ProvidesHealing{ heal_amount: 8 })
+        .build()
+}
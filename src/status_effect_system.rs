@@ -0,0 +1,63 @@
+use specs::prelude::*;
+use super::{StatusEffects, StatusKind, SufferDamage, DamageType, CombatStats, initiative_system::GameClock,
+            game_events::{GameEvents, GameEvent}};
+
+/// Asleep and paralysed creatures lose their actions. Haste and slow work through
+/// `initiative_system::effective_speed` instead.
+pub fn can_act(statuses : Option<&StatusEffects>) -> bool {
+    match statuses {
+        None => true,
+        Some(statuses) => !statuses.has(StatusKind::Sleep) && !statuses.has(StatusKind::Paralysis)
+    }
+}
+
+pub fn player_can_act(ecs : &World) -> bool {
+    can_act(ecs.read_storage::<StatusEffects>().get(*ecs.fetch::<Entity>()))
+}
+
+/// Ticks every running status effect once per game turn, applying damage and healing over time
+/// and announcing effects as they wear off.
+pub struct StatusEffectSystem {}
+
+impl<'a> System<'a> for StatusEffectSystem {
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, GameClock>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteStorage<'a, StatusEffects>,
+                        WriteStorage<'a, SufferDamage>,
+                        WriteStorage<'a, CombatStats> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, clock, mut events, mut statuses, mut suffer_damage, mut combat_stats) = data;
+
+        if !clock.ticked { return; }
+
+        let mut cleared : Vec<Entity> = Vec::new();
+        for (entity, statuses) in (&entities, &mut statuses).join() {
+            for effect in statuses.effects.iter_mut() {
+                match effect.kind {
+                    StatusKind::Poison => SufferDamage::new_damage(&mut suffer_damage, entity, effect.magnitude, DamageType::Poison, None),
+                    StatusKind::Burning => SufferDamage::new_damage(&mut suffer_damage, entity, effect.magnitude, DamageType::Fire, None),
+                    StatusKind::Regeneration => {
+                        if let Some(stats) = combat_stats.get_mut(entity) {
+                            stats.hp = i32::min(stats.max_hp, stats.hp + effect.magnitude);
+                        }
+                    }
+                    _ => {}
+                }
+                effect.turns -= 1;
+                if effect.turns < 1 {
+                    events.publish(GameEvent::StatusExpired{ target: entity, kind: effect.kind });
+                }
+            }
+            statuses.effects.retain(|effect| effect.turns > 0);
+            if statuses.effects.is_empty() {
+                cleared.push(entity);
+            }
+        }
+
+        for entity in cleared.iter() {
+            statuses.remove(*entity);
+        }
+    }
+}
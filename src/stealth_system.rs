@@ -0,0 +1,112 @@
+use specs::prelude::*;
+use rltk::{Point, RandomNumberGenerator, BaseMap};
+use super::{Map, Unaware, Sneaking, Viewshed, Position, Attributes, Skills, gamesystem,
+            initiative_system::GameClock, game_events::{GameEvents, GameEvent}};
+
+/// How far (in steps through the map) various noises carry.
+pub const WALK_NOISE : i32 = 6;
+pub const SNEAK_NOISE : i32 = 2;
+pub const FIGHT_NOISE : i32 = 10;
+pub const ITEM_NOISE : i32 = 5;
+
+/// Base difficulty of noticing someone, before their stealth.
+const BASE_NOTICE_DC : i32 = 10;
+/// Extra difficulty of noticing someone who is sneaking.
+const SNEAKING_BONUS : i32 = 5;
+/// Sleeping monsters are hard to rouse.
+const ASLEEP_PENALTY : i32 = 5;
+
+pub struct Noise {
+    pub x : i32,
+    pub y : i32,
+    pub radius : i32
+}
+
+/// Noises made since perception was last checked.
+#[derive(Default)]
+pub struct NoiseQueue {
+    noises : Vec<Noise>
+}
+
+impl NoiseQueue {
+    pub fn new() -> NoiseQueue {
+        NoiseQueue::default()
+    }
+
+    pub fn make(&mut self, x : i32, y : i32, radius : i32) {
+        self.noises.push(Noise{ x, y, radius });
+    }
+}
+
+/// Gives unaware monsters a chance to notice the player, against the player's stealth.
+/// Every noise is heard by monsters it can reach by walking, the closer the easier;
+/// monsters that are awake also get a check each turn the player is in view.
+pub struct PerceptionSystem {}
+
+impl<'a> System<'a> for PerceptionSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( Entities<'a>,
+                        ReadExpect<'a, Map>,
+                        ReadExpect<'a, Point>,
+                        ReadExpect<'a, Entity>,
+                        ReadExpect<'a, GameClock>,
+                        WriteExpect<'a, NoiseQueue>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        WriteExpect<'a, GameEvents>,
+                        WriteStorage<'a, Unaware>,
+                        ReadStorage<'a, Viewshed>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, Attributes>,
+                        ReadStorage<'a, Skills>,
+                        ReadStorage<'a, Sneaking> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, map, player_pos, player_entity, clock, mut noise_queue, mut rng, mut events,
+            mut unaware, viewsheds, positions, attributes, skills, sneaking) = data;
+
+        let noises : Vec<Noise> = noise_queue.noises.drain(..).collect();
+        if (&unaware).join().next().is_none() { return; }
+
+        let mut difficulty = BASE_NOTICE_DC + gamesystem::stealth(&attributes.get(*player_entity).cloned().unwrap_or_default(),
+            &skills.get(*player_entity).cloned().unwrap_or_default());
+        if sneaking.get(*player_entity).is_some() {
+            difficulty += SNEAKING_BONUS;
+        }
+
+        let mut noticed : Vec<Entity> = Vec::new();
+        for noise in noises.iter() {
+            let start = map.xy_index(noise.x, noise.y);
+            let heard = rltk::DijkstraMap::new(map.width, map.height, &[start], &*map, noise.radius as f32);
+            for (entity, listener, pos) in (&entities, &unaware, &positions).join() {
+                // Creatures block their own tile, so measure to the nearest open tile beside them
+                let index = map.xy_index(pos.x, pos.y);
+                let distance = map.get_available_exits(index).iter()
+                    .map(|(exit, cost)| heard.map[*exit] + cost)
+                    .fold(heard.map[index], f32::min);
+                if distance > noise.radius as f32 || noticed.contains(&entity) { continue; }
+                let mut roll = rng.roll_dice(1, 20) + gamesystem::perception(&attributes.get(entity).cloned().unwrap_or_default());
+                roll += noise.radius - distance as i32;
+                if listener.asleep { roll -= ASLEEP_PENALTY; }
+                if roll >= difficulty {
+                    noticed.push(entity);
+                }
+            }
+        }
+
+        if clock.ticked {
+            for (entity, listener, viewshed) in (&entities, &unaware, &viewsheds).join() {
+                if listener.asleep || noticed.contains(&entity) || !viewshed.visible_tiles.contains(&*player_pos) { continue; }
+                let roll = rng.roll_dice(1, 20) + gamesystem::perception(&attributes.get(entity).cloned().unwrap_or_default());
+                if roll >= difficulty {
+                    noticed.push(entity);
+                }
+            }
+        }
+
+        for entity in noticed {
+            if let Some(listener) = unaware.remove(entity) {
+                events.publish(GameEvent::Noticed{ entity, was_asleep: listener.asleep });
+            }
+        }
+    }
+}